@@ -48,4 +48,8 @@ pub trait Environment: Clone + Send + Sync + 'static {
     fn is_dir(&self, root: &Path) -> bool;
 
     async fn sleep(&self, duration: Duration);
+
+    /// The time elapsed since an unspecified point in time
+    /// from a monotonic clock, only meant for measuring durations.
+    fn now(&self) -> Duration;
 }
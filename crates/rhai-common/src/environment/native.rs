@@ -2,10 +2,14 @@ use super::Environment;
 use async_trait::async_trait;
 use std::{
     path::{Path, PathBuf},
-    time::Duration,
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 use url::Url;
 
+/// The point in time [`NativeEnvironment::now`] is measured from.
+static START: OnceLock<Instant> = OnceLock::new();
+
 #[derive(Debug, Clone)]
 pub struct NativeEnvironment;
 
@@ -93,6 +97,10 @@ impl Environment for NativeEnvironment {
         tokio::time::sleep(duration).await;
     }
 
+    fn now(&self) -> Duration {
+        START.get_or_init(Instant::now).elapsed()
+    }
+
     fn is_dir(&self, root: &Path) -> bool {
         root.is_dir()
     }
@@ -1,5 +1,6 @@
 use crate::Symbol;
 use thiserror::Error;
+use url::Url;
 
 #[derive(Debug, Clone, Error)]
 #[error("{kind}")]
@@ -29,8 +30,19 @@ pub enum ErrorKind {
         reference_symbol: Symbol,
         similar_name: Option<String>,
     },
-    #[error("unresolved import")]
-    UnresolvedImport { import: Symbol },
+    #[error(
+        "unresolved import{}",
+        match import_url {
+            Some(url) => format!(", no module found at `{url}`"),
+            None => String::from(", the import path is not a valid URL or relative path"),
+        }
+    )]
+    UnresolvedImport {
+        import: Symbol,
+        /// The module URL the import path resolves to,
+        /// `None` if it is not a valid URL or relative path.
+        import_url: Option<Url>,
+    },
     #[error("nested functions are not allowed")]
     NestedFunction { function: Symbol },
 }
@@ -12,7 +12,7 @@ use crate::{
     source::{Source, SourceData},
     symbol::*,
     ty::{Type, TypeData},
-    Module, Scope,
+    HashMap, IndexSet, Module, Scope,
 };
 
 use rhai_rowan::syntax::SyntaxNode;
@@ -29,6 +29,9 @@ pub struct Hir {
     sources: SlotMap<Source, SourceData>,
     types: SlotMap<Type, TypeData>,
     builtin_types: BuiltinTypes,
+    /// Symbols by their source so that queries in a single
+    /// document don't have to scan all symbols.
+    source_symbols: HashMap<Source, IndexSet<Symbol>>,
 }
 
 impl Default for Hir {
@@ -42,6 +45,7 @@ impl Default for Hir {
             sources: Default::default(),
             types: Default::default(),
             builtin_types: BuiltinTypes::uninit(),
+            source_symbols: Default::default(),
         };
         this.prepare();
         this
@@ -64,6 +68,7 @@ impl Hir {
         self.modules.clear();
         self.sources.clear();
         self.types.clear();
+        self.source_symbols.clear();
         self.builtin_types = BuiltinTypes::uninit();
         self.static_module = Module::null();
         self.prepare();
@@ -80,6 +85,15 @@ impl Hir {
         self.symbols.iter()
     }
 
+    /// Symbols that originate from the given source.
+    pub fn source_symbols(&self, source: Source) -> impl Iterator<Item = (Symbol, &SymbolData)> {
+        self.source_symbols
+            .get(&source)
+            .into_iter()
+            .flatten()
+            .filter_map(|&symbol| self.symbols.get(symbol).map(|data| (symbol, data)))
+    }
+
     #[must_use]
     #[inline]
     pub fn scope(&self, scope: Scope) -> Option<&ScopeData> {
@@ -113,6 +127,11 @@ impl Hir {
         self.sources.iter()
     }
 
+    #[inline]
+    pub fn types(&self) -> impl Iterator<Item = (Type, &TypeData)> {
+        self.types.iter()
+    }
+
     #[must_use]
     pub fn source_of(&self, url: &Url) -> Option<Source> {
         self.sources()
@@ -2,7 +2,7 @@ use super::*;
 use crate::{
     module::ModuleKind,
     source::SourceInfo,
-    ty::{Array, Object},
+    ty::{Array, Function, Object},
     util::script_url,
     IndexSet,
 };
@@ -153,7 +153,7 @@ impl Hir {
 
                 let ty_decl = const_def.ty().map(|t| self.add_type(source, None, &t));
 
-                let symbol = self.symbols.insert(SymbolData {
+                let symbol = self.add_symbol(SymbolData {
                     export: true,
                     source: SourceInfo {
                         source: Some(source),
@@ -183,7 +183,7 @@ impl Hir {
 
                 let ty_decl = let_def.ty().map(|t| self.add_type(source, None, &t));
 
-                let symbol = self.symbols.insert(SymbolData {
+                let symbol = self.add_symbol(SymbolData {
                     export: false,
                     source: SourceInfo {
                         source: Some(source),
@@ -312,7 +312,7 @@ impl Hir {
                     ret_ty = self.add_type(source, None, &t);
                 }
 
-                let symbol = self.symbols.insert(SymbolData {
+                let symbol = self.add_symbol(SymbolData {
                     export: true,
                     source: SourceInfo {
                         source: Some(source),
@@ -427,7 +427,7 @@ impl Hir {
                         ty: self.builtin_types.unknown,
                     });
 
-                    scope.add_symbol(self, symbol, false);
+                    scope.add_symbol(self, symbol, true);
                 }
             }
         }
@@ -440,6 +440,28 @@ impl Hir {
         ty: &ast::Type,
     ) -> Type {
         match &ty {
+            ast::Type::Ident(ident)
+                if ident
+                    .ident_token()
+                    .map_or(false, |t| t.text().trim() == "Iterator") =>
+            {
+                let items = ident
+                    .generics()
+                    .and_then(|generics| generics.types().next())
+                    .map_or(self.builtin_types.unknown, |items| {
+                        self.add_type(source, None, &items)
+                    });
+
+                self.types.insert(TypeData {
+                    source: SourceInfo {
+                        source: Some(source),
+                        text_range: Some(ty.syntax().text_range()),
+                        selection_text_range,
+                    },
+                    protected: false,
+                    kind: TypeKind::Iterator(items),
+                })
+            }
             ast::Type::Ident(ident) => self.types.insert(TypeData {
                 source: SourceInfo {
                     source: Some(source),
@@ -537,6 +559,32 @@ impl Hir {
                     kind: TypeKind::Tuple(types),
                 })
             }
+            ast::Type::Fn(f) => {
+                let params = f
+                    .type_list()
+                    .into_iter()
+                    .flat_map(|list| list.types())
+                    .map(|ty| (String::new(), self.add_type(source, None, &ty)))
+                    .collect::<Vec<_>>();
+
+                let ret = f.ret_ty().map_or(self.builtin_types.void, |ret_ty| {
+                    self.add_type(source, None, &ret_ty)
+                });
+
+                self.types.insert(TypeData {
+                    source: SourceInfo {
+                        source: Some(source),
+                        text_range: Some(f.syntax().text_range()),
+                        selection_text_range,
+                    },
+                    protected: false,
+                    kind: TypeKind::Fn(Function {
+                        is_closure: false,
+                        params,
+                        ret,
+                    }),
+                })
+            }
             ast::Type::Unknown(_) => self.builtin_types.unknown,
         }
     }
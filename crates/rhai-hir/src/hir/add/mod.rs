@@ -42,6 +42,39 @@ impl Hir {
             self.add_def(source, &def);
         }
     }
+
+    /// Moves a script source and its module to a new URL
+    /// while keeping all of its symbols.
+    ///
+    /// Returns `false` if the source cannot be moved in place,
+    /// in that case it has to be added again with the new URL.
+    pub fn rename_source(&mut self, source: Source, url: &Url) -> bool {
+        let source_data = match self.sources.get(source) {
+            Some(s) => s,
+            None => return false,
+        };
+
+        if !source_data.kind.is_script() || self.source_of(url).is_some() {
+            return false;
+        }
+
+        // Definitions might also describe the module of the script,
+        // in that case they would have to be moved as well.
+        let module = source_data.module;
+        match self.modules.get(module) {
+            Some(m) if m.sources.len() == 1 && m.url() == Some(&source_data.url) => {}
+            _ => return false,
+        }
+
+        if self.module_by_url(url).is_some() {
+            return false;
+        }
+
+        self.source_mut(source).url = url.clone();
+        self.module_mut(module).kind = ModuleKind::Url(url.clone());
+
+        true
+    }
 }
 
 impl Hir {
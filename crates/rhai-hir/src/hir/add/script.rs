@@ -559,7 +559,7 @@ impl Hir {
                         selection_text_range: None,
                     },
                     kind: SymbolKind::Closure(ClosureSymbol {
-                        scope,
+                        scope: closure_scope,
                         expr: closure_expr_symbol,
                     }),
                     ty: self.builtin_types.unknown,
@@ -683,6 +683,8 @@ impl Hir {
                     ..ScopeData::default()
                 });
 
+                let mut pat_decls = Vec::new();
+
                 if let Some(pat) = expr.pat() {
                     for ident in pat.idents() {
                         let ident_symbol = self.add_symbol(SymbolData {
@@ -702,6 +704,7 @@ impl Hir {
                             ty: self.builtin_types.unknown,
                         });
                         scope.add_symbol(self, ident_symbol, false);
+                        pat_decls.push(ident_symbol);
                     }
                 }
 
@@ -721,6 +724,7 @@ impl Hir {
                         iterable: expr
                             .iterable()
                             .and_then(|expr| self.add_expression(source, scope, false, expr)),
+                        pat_decls,
                         scope: for_scope,
                     }),
                     ty: self.builtin_types.unknown,
@@ -821,8 +825,10 @@ impl Hir {
                                 let mut left = None;
                                 let mut right = None;
 
-                                if let Some(discard) = arm.discard_token() {
-                                    left = Some(self.add_symbol(SymbolData {
+                                let discard = arm.discard_token();
+
+                                if let Some(discard) = &discard {
+                                    let discard_symbol = self.add_symbol(SymbolData {
                                         export: false,
                                         source: SourceInfo {
                                             source: Some(source),
@@ -832,14 +838,19 @@ impl Hir {
                                         parent_scope: Scope::default(),
                                         kind: SymbolKind::Discard(DiscardSymbol {}),
                                         ty: self.builtin_types.unknown,
-                                    }));
+                                    });
+                                    scope.add_symbol(self, discard_symbol, false);
+                                    left = Some(discard_symbol);
                                 }
 
                                 if let Some(expr) = arm.condition().and_then(|c| c.expr()) {
                                     left = self.add_expression(source, scope, false, expr);
                                 }
 
-                                if let Some(expr) = arm.pattern_expr() {
+                                // The value would be mistaken for
+                                // the pattern of a discard arm.
+                                if let Some(expr) = arm.pattern_expr().filter(|_| discard.is_none())
+                                {
                                     left = self.add_expression(source, scope, false, expr);
                                 }
 
@@ -1235,7 +1246,17 @@ fn extract_doc_definitions(item: &Item) -> Vec<(TextSize, String)> {
 
 impl Hir {
     pub(super) fn add_symbol(&mut self, data: SymbolData) -> Symbol {
-        self.symbols.insert(data)
+        let source = data.source.source;
+        let symbol = self.symbols.insert(data);
+
+        if let Some(source) = source {
+            self.source_symbols
+                .entry(source)
+                .or_default()
+                .insert(symbol);
+        }
+
+        symbol
     }
 
     pub(super) fn add_scope(&mut self, data: ScopeData) -> Scope {
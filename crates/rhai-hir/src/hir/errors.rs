@@ -85,8 +85,19 @@ impl Hir {
                 }
                 SymbolKind::Import(import) => {
                     if import.target.is_none() {
+                        let from = self
+                            .module_by_symbol(symbol)
+                            .and_then(|m| self[m].url().cloned());
+
+                        let import_url = import
+                            .import_path(self)
+                            .and_then(|path| self.resolve_import_url(from.as_ref(), path));
+
                         errors.push(Error {
-                            kind: ErrorKind::UnresolvedImport { import: symbol },
+                            kind: ErrorKind::UnresolvedImport {
+                                import: symbol,
+                                import_url,
+                            },
                         });
                     }
                 }
@@ -26,8 +26,7 @@ impl Hir {
         offset: TextSize,
         inclusive: bool,
     ) -> Option<Symbol> {
-        self.symbols()
-            .filter(|(_, d)| d.source.is(source))
+        self.source_symbols(source)
             .filter_map(|(sym, d)| {
                 d.source.selection_text_range.and_then(|range| {
                     if (inclusive && range.contains_inclusive(offset)) || range.contains(offset) {
@@ -43,8 +42,7 @@ impl Hir {
 
     #[must_use]
     pub fn symbol_at(&self, source: Source, offset: TextSize, inclusive: bool) -> Option<Symbol> {
-        self.symbols()
-            .filter(|(_, d)| d.source.is(source))
+        self.source_symbols(source)
             .filter_map(|(sym, d)| {
                 d.source.text_range.and_then(|range| {
                     if (inclusive && range.contains_inclusive(offset)) || range.contains(offset) {
@@ -76,6 +74,33 @@ impl Hir {
             .or_else(|| self.module_by_source(source).map(|m| self[m].scope))
     }
 
+    /// Returns whether the given declaration or reference
+    /// writes or only reads the symbol.
+    #[must_use]
+    pub fn symbol_access(&self, symbol: Symbol) -> SymbolAccess {
+        let symbol_data = &self[symbol];
+
+        match &symbol_data.kind {
+            SymbolKind::Decl(_) | SymbolKind::Fn(_) => SymbolAccess::Write,
+            SymbolKind::Reference(_) => {
+                let assignment = self
+                    .scopes
+                    .get(symbol_data.parent_scope)
+                    .and_then(|scope| scope.parent)
+                    .and_then(|parent| parent.as_symbol().copied())
+                    .and_then(|parent| self[parent].kind.as_binary());
+
+                match assignment {
+                    Some(binary) if binary.is_assignment() && binary.lhs == Some(symbol) => {
+                        SymbolAccess::Write
+                    }
+                    _ => SymbolAccess::Read,
+                }
+            }
+            _ => SymbolAccess::Read,
+        }
+    }
+
     #[must_use]
     pub fn source_by_url(&self, url: &Url) -> Option<Source> {
         for (src, data) in self.sources.iter() {
@@ -1,4 +1,4 @@
-use crate::{hir::BuiltinTypes, Hir};
+use crate::{hir::BuiltinTypes, symbol::SymbolKind, ty::Type, HashSet, Hir, Symbol, TypeKind};
 
 impl Hir {
     #[must_use]
@@ -6,4 +6,110 @@ impl Hir {
     pub const fn builtin_types(&self) -> BuiltinTypes {
         self.builtin_types
     }
+
+    /// Returns the type declarations of the aliases that
+    /// appear in the given type, e.g. both `Foo` and `Bar` for `Foo | [Bar]`.
+    #[must_use]
+    pub fn type_decls_of(&self, ty: Type) -> Vec<Symbol> {
+        let mut aliased = Vec::new();
+        self.collect_aliased_types(ty, &mut HashSet::default(), &mut aliased);
+
+        aliased
+            .into_iter()
+            .flat_map(|aliased_ty| {
+                self.symbols()
+                    .filter_map(move |(symbol, data)| match &data.kind {
+                        SymbolKind::TypeDecl(decl) => match &self[decl.ty].kind {
+                            TypeKind::Alias(_, decl_ty) if *decl_ty == aliased_ty => Some(symbol),
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the object literal the given object type was inferred from.
+    ///
+    /// Types extended by field assignments after the declaration
+    /// keep the source of the original literal.
+    #[must_use]
+    pub fn object_literal_of(&self, ty: Type) -> Option<Symbol> {
+        let ty_data = self.types.get(self.follow_aliases(ty))?;
+
+        if !ty_data.kind.is_object() {
+            return None;
+        }
+
+        let range = ty_data.source.text_range?;
+
+        self.source_symbols(ty_data.source.source?)
+            .find_map(|(symbol, data)| {
+                (data.kind.is_object() && data.source.text_range == Some(range)).then_some(symbol)
+            })
+    }
+
+    /// Returns the field access (`lhs.field`) if the given symbol is its field.
+    #[must_use]
+    pub fn field_access_of(&self, field: Symbol) -> Option<Symbol> {
+        let scope = self.symbols.get(field)?.parent_scope;
+        let access = *self.scopes.get(scope)?.parent?.as_symbol()?;
+
+        let binary = self.symbols.get(access)?.kind.as_binary()?;
+
+        (binary.is_field_access() && binary.rhs == Some(field)).then_some(access)
+    }
+
+    /// Returns the fields of accesses to the field with the given name
+    /// on objects inferred from the given object literal.
+    pub fn object_field_accesses<'h>(
+        &'h self,
+        literal: Symbol,
+        field: &'h str,
+    ) -> impl Iterator<Item = Symbol> + 'h {
+        self.symbols.iter().filter_map(move |(_, data)| {
+            let binary = data.kind.as_binary().filter(|b| b.is_field_access())?;
+            let rhs = binary.rhs?;
+
+            if self.symbols.get(rhs)?.name(self)? != field {
+                return None;
+            }
+
+            let lhs_ty = self.symbols.get(binary.lhs?)?.ty;
+
+            (self.object_literal_of(lhs_ty)? == literal).then_some(rhs)
+        })
+    }
+
+    fn collect_aliased_types(&self, ty: Type, seen: &mut HashSet<Type>, aliased: &mut Vec<Type>) {
+        if !seen.insert(ty) {
+            return;
+        }
+
+        let ty_data = match self.types.get(ty) {
+            Some(t) => t,
+            None => return,
+        };
+
+        match &ty_data.kind {
+            TypeKind::Alias(_, alias_ty) => {
+                if !aliased.contains(alias_ty) {
+                    aliased.push(*alias_ty);
+                }
+            }
+            TypeKind::Array(arr) => self.collect_aliased_types(arr.items, seen, aliased),
+            TypeKind::Iterator(item) => self.collect_aliased_types(*item, seen, aliased),
+            TypeKind::Union(types) => {
+                for ty in types {
+                    self.collect_aliased_types(*ty, seen, aliased);
+                }
+            }
+            TypeKind::Tuple(types) => {
+                for ty in types {
+                    self.collect_aliased_types(*ty, seen, aliased);
+                }
+            }
+            _ => {}
+        }
+    }
 }
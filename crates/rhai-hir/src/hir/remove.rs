@@ -11,11 +11,9 @@ impl Hir {
         self.sources.remove(source);
 
         let symbols_to_remove = self
-            .symbols
-            .iter()
-            .filter(|(_, symbol_data)| symbol_data.source.is(source))
-            .map(|(s, _)| s)
-            .collect::<Vec<_>>();
+            .source_symbols
+            .remove(&source)
+            .unwrap_or_default();
 
         let types_to_remove = self
             .types
@@ -112,6 +110,12 @@ impl Hir {
     /// and then remove the symbol itself.
     fn remove_symbol(&mut self, symbol: Symbol) {
         if let Some(s) = self.symbols.remove(symbol) {
+            if let Some(source) = s.source.source {
+                if let Some(symbols) = self.source_symbols.get_mut(&source) {
+                    symbols.remove(&symbol);
+                }
+            }
+
             if self.scopes.contains_key(s.parent_scope) {
                 self.scope_mut(s.parent_scope).symbols.shift_remove(&symbol);
                 self.scope_mut(s.parent_scope)
@@ -5,6 +5,8 @@ use crate::{
 use itertools::Itertools;
 use url::Url;
 
+mod narrowing;
+mod objects;
 mod types;
 
 impl Hir {
@@ -19,6 +21,7 @@ impl Hir {
                     d.references.clear();
                 }
                 SymbolKind::Reference(r) => r.target = None,
+                SymbolKind::Import(i) => i.target = None,
                 _ => {}
             }
         }
@@ -216,6 +219,12 @@ impl Hir {
                                     _ => {}
                                 }
 
+                                if let SymbolKind::Decl(alias_decl) =
+                                    &mut self.symbol_mut(visible_symbol).kind
+                                {
+                                    alias_decl.references.insert(module_reference);
+                                }
+
                                 break;
                             }
                         }
@@ -263,8 +272,11 @@ impl Hir {
         }
     }
 
+    /// Resolve the URL of an imported module
+    /// relative to the URL of the importing module.
     #[allow(clippy::unused_self)]
-    pub(super) fn resolve_import_url(&self, from: Option<&Url>, import_path: &str) -> Option<Url> {
+    #[must_use]
+    pub fn resolve_import_url(&self, from: Option<&Url>, import_path: &str) -> Option<Url> {
         if import_path.starts_with('.') {
             match from {
                 Some(from) => match from.join(import_path) {
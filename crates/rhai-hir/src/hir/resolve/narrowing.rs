@@ -0,0 +1,357 @@
+use crate::{
+    eval::Value,
+    scope::ScopeParent,
+    source::SourceInfo,
+    symbol::{ReferenceTarget, SymbolKind},
+    ty::{Array, Object, Type, TypeData},
+    Hir, IndexMap, IndexSet, Symbol, TypeKind,
+};
+
+/// A type check on a declaration that is known
+/// to hold in a branch.
+#[derive(Debug, Clone)]
+enum Guard {
+    /// The declaration is of the given type.
+    Is(TypeKind),
+    /// The declaration is not of the given type.
+    IsNot(TypeKind),
+}
+
+impl Hir {
+    /// Narrow the type of a reference to a declaration
+    /// based on the type checks of the branches the reference is in.
+    ///
+    /// Recognised checks are `type_of(x) == "string"` and `x.is_string()`
+    /// in `if` conditions and `switch type_of(x)` arms.
+    pub(super) fn narrowed_reference_type(
+        &mut self,
+        reference: Symbol,
+        decl: Symbol,
+        ty: Type,
+    ) -> Type {
+        let mut guards = Vec::new();
+
+        // Narrowed types belong to the reference, so that
+        // they are removed together with its source.
+        let source = self.symbols.get(reference).unwrap().source;
+
+        let mut child = reference;
+
+        loop {
+            let scope = self.symbols.get(child).unwrap().parent_scope;
+
+            let scope_data = match self.scopes.get(scope) {
+                Some(s) => s,
+                None => break,
+            };
+
+            let switch_symbols = scope_data
+                .hoisted_symbols
+                .iter()
+                .copied()
+                .filter(|&sym| self.symbols.get(sym).unwrap().kind.is_switch())
+                .collect::<Vec<_>>();
+
+            for switch_symbol in switch_symbols {
+                if let Some(guard) = self.switch_arm_guard(switch_symbol, child, decl) {
+                    guards.push(guard);
+                }
+            }
+
+            let parent = match scope_data.parent {
+                Some(ScopeParent::Symbol(parent)) => parent,
+                _ => break,
+            };
+
+            let parent_data = self.symbols.get(parent).unwrap();
+
+            if parent_data.kind.is_fn() {
+                break;
+            }
+
+            if let Some(if_symbol) = parent_data.kind.as_if() {
+                let branch_conditions = if_symbol
+                    .branches
+                    .iter()
+                    .map(|(condition, branch_scope)| (*condition, *branch_scope))
+                    .collect::<Vec<_>>();
+
+                for (condition, branch_scope) in branch_conditions {
+                    let condition = match condition {
+                        Some(c) => c,
+                        None => break,
+                    };
+
+                    if branch_scope == scope {
+                        guards.extend(self.condition_guards(condition, decl));
+                        break;
+                    }
+
+                    // Every previous branch condition was false,
+                    // this is only useful for simple conditions.
+                    if let [Guard::Is(kind)] = &self.condition_guards(condition, decl)[..] {
+                        guards.push(Guard::IsNot(kind.clone()));
+                    }
+                }
+            }
+
+            child = parent;
+        }
+
+        let mut narrowed = ty;
+
+        // Outer branches first.
+        for guard in guards.into_iter().rev() {
+            narrowed = match guard {
+                Guard::Is(kind) => self.narrow_type_to(narrowed, &kind, source),
+                Guard::IsNot(kind) => self.narrow_type_without(narrowed, &kind, source),
+            };
+        }
+
+        narrowed
+    }
+
+    /// Collect the type checks on the given declaration
+    /// that hold if the condition is true.
+    fn condition_guards(&self, condition: Symbol, decl: Symbol) -> Vec<Guard> {
+        let condition_data = self.symbols.get(condition).unwrap();
+
+        match &condition_data.kind {
+            SymbolKind::Binary(binary) if binary.lookup_text == "&&" => {
+                let (lhs, rhs) = (binary.lhs, binary.rhs);
+
+                lhs.into_iter()
+                    .chain(rhs)
+                    .flat_map(|operand| self.condition_guards(operand, decl))
+                    .collect()
+            }
+            SymbolKind::Binary(binary) if binary.lookup_text == "==" => {
+                let operands = match (binary.lhs, binary.rhs) {
+                    (Some(lhs), Some(rhs)) => [(lhs, rhs), (rhs, lhs)],
+                    _ => return Vec::new(),
+                };
+
+                operands
+                    .into_iter()
+                    .find_map(|(call, lit)| {
+                        if self.type_of_target(call)? != decl {
+                            return None;
+                        }
+
+                        let type_name = self.string_lit(lit)?;
+                        self.guard_kind(&type_name).map(Guard::Is)
+                    })
+                    .into_iter()
+                    .collect()
+            }
+            SymbolKind::Call(call) if call.arguments.is_empty() => {
+                let method_call = match call
+                    .lhs
+                    .and_then(|lhs| self.symbols.get(lhs).unwrap().kind.as_binary())
+                    .filter(|binary| binary.is_field_access())
+                {
+                    Some(b) => b,
+                    None => return Vec::new(),
+                };
+
+                let (receiver, method) = match (method_call.lhs, method_call.rhs) {
+                    (Some(receiver), Some(method)) => (receiver, method),
+                    _ => return Vec::new(),
+                };
+
+                if self.reference_target(receiver) != Some(decl) {
+                    return Vec::new();
+                }
+
+                let type_name = match self.symbols.get(method).unwrap().kind.as_reference() {
+                    Some(r) => match r.name.strip_prefix("is_") {
+                        Some(type_name) => type_name.to_string(),
+                        None => return Vec::new(),
+                    },
+                    None => return Vec::new(),
+                };
+
+                self.guard_kind(&type_name)
+                    .map(Guard::Is)
+                    .into_iter()
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The type check for `child` if it is in the value of
+    /// an arm of a `switch type_of(decl)` expression.
+    fn switch_arm_guard(
+        &self,
+        switch_symbol: Symbol,
+        child: Symbol,
+        decl: Symbol,
+    ) -> Option<Guard> {
+        let switch = self.symbols.get(switch_symbol)?.kind.as_switch()?;
+
+        let pat_expr = switch
+            .arms
+            .iter()
+            .find(|arm| {
+                arm.value_expr
+                    .map_or(false, |value_expr| self.is_within(child, value_expr))
+            })?
+            .pat_expr?;
+
+        if self.type_of_target(switch.target?)? != decl {
+            return None;
+        }
+
+        let type_name = self.string_lit(pat_expr)?;
+        self.guard_kind(&type_name).map(Guard::Is)
+    }
+
+    /// Whether the symbol is the given expression or a part of it.
+    fn is_within(&self, symbol: Symbol, expr: Symbol) -> bool {
+        if symbol == expr {
+            return true;
+        }
+
+        let symbol_source = self.symbols.get(symbol).unwrap().source;
+        let expr_source = self.symbols.get(expr).unwrap().source;
+
+        match (
+            symbol_source.source.zip(symbol_source.text_range),
+            expr_source.source.zip(expr_source.text_range),
+        ) {
+            (Some((source, range)), Some((expr_source, expr_range))) => {
+                source == expr_source && expr_range.contains_range(range)
+            }
+            _ => false,
+        }
+    }
+
+    /// The declaration `x` if the symbol is `type_of(x)`.
+    fn type_of_target(&self, call: Symbol) -> Option<Symbol> {
+        let call = self.symbols.get(call)?.kind.as_call()?;
+
+        if call.arguments.len() != 1 {
+            return None;
+        }
+
+        let callee = self.symbols.get(call.lhs?)?.kind.as_reference()?;
+
+        if callee.name != "type_of" {
+            return None;
+        }
+
+        self.reference_target(call.arguments[0])
+    }
+
+    fn reference_target(&self, reference: Symbol) -> Option<Symbol> {
+        match self.symbols.get(reference)?.kind.as_reference()?.target {
+            Some(ReferenceTarget::Symbol(target)) => Some(target),
+            _ => None,
+        }
+    }
+
+    fn string_lit(&self, lit: Symbol) -> Option<String> {
+        match &self.symbols.get(lit)?.kind.as_lit()?.value {
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    /// The type kind for a type name as returned by `type_of`
+    /// or used in `is_*` methods.
+    fn guard_kind(&self, type_name: &str) -> Option<TypeKind> {
+        let kind = match type_name {
+            "string" | "String" => TypeKind::String,
+            "int" | "i64" | "i32" | "INT" => TypeKind::Int,
+            "float" | "f64" | "f32" | "FLOAT" => TypeKind::Float,
+            "bool" => TypeKind::Bool,
+            "char" => TypeKind::Char,
+            "timestamp" => TypeKind::Timestamp,
+            "()" | "unit" => TypeKind::Void,
+            "array" => TypeKind::Array(Array {
+                items: self.builtin_types.unknown,
+            }),
+            "map" => TypeKind::Object(Object {
+                fields: IndexMap::default(),
+            }),
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Whether the type is of the given kind, e.g. both are arrays.
+    fn is_type_kind(&self, ty: Type, kind: &TypeKind) -> bool {
+        let ty = self.follow_aliases(ty);
+
+        core::mem::discriminant(&self.types.get(ty).unwrap().kind) == core::mem::discriminant(kind)
+    }
+
+    fn type_of_kind(&mut self, kind: &TypeKind, source: SourceInfo) -> Type {
+        match kind {
+            TypeKind::String => self.builtin_types.string,
+            TypeKind::Int => self.builtin_types.int,
+            TypeKind::Float => self.builtin_types.float,
+            TypeKind::Bool => self.builtin_types.bool,
+            TypeKind::Char => self.builtin_types.char,
+            TypeKind::Timestamp => self.builtin_types.timestamp,
+            TypeKind::Void => self.builtin_types.void,
+            kind => self.types.insert(TypeData {
+                source,
+                kind: kind.clone(),
+                protected: false,
+            }),
+        }
+    }
+
+    fn union_members(&self, ty: Type) -> Option<Vec<Type>> {
+        self.types
+            .get(self.follow_aliases(ty))
+            .unwrap()
+            .kind
+            .as_union()
+            .map(|members| members.iter().copied().collect())
+    }
+
+    fn narrow_type_to(&mut self, ty: Type, kind: &TypeKind, source: SourceInfo) -> Type {
+        let members = self.union_members(ty).unwrap_or_else(|| vec![ty]);
+
+        let mut matching = members
+            .into_iter()
+            .filter(|&member| self.is_type_kind(member, kind))
+            .collect::<IndexSet<_>>();
+
+        match matching.len() {
+            0 => self.type_of_kind(kind, source),
+            1 => matching.pop().unwrap(),
+            _ => self.types.insert(TypeData {
+                source,
+                kind: TypeKind::Union(matching),
+                protected: false,
+            }),
+        }
+    }
+
+    fn narrow_type_without(&mut self, ty: Type, kind: &TypeKind, source: SourceInfo) -> Type {
+        let members = match self.union_members(ty) {
+            Some(members) => members,
+            None => return ty,
+        };
+
+        let mut remaining = members
+            .into_iter()
+            .filter(|&member| !self.is_type_kind(member, kind))
+            .collect::<IndexSet<_>>();
+
+        match remaining.len() {
+            0 => self.builtin_types.never,
+            1 => remaining.pop().unwrap(),
+            _ => self.types.insert(TypeData {
+                source,
+                kind: TypeKind::Union(remaining),
+                protected: false,
+            }),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    eval::Value,
+    scope::ScopeParent,
+    symbol::SymbolKind,
+    ty::{Object, Type, TypeData},
+    HashSet, Hir, IndexMap, Scope, Symbol, TypeKind,
+};
+
+/// A field assignment to an object declaration,
+/// e.g. `m.name = "x"` or `m["name"] = "x"`.
+struct FieldAssignment {
+    name: String,
+    value: Option<Symbol>,
+    /// The `if` branches the assignment is conditional on,
+    /// the outermost first.
+    branches: Vec<(Symbol, usize)>,
+    /// Whether the assignment happens in a loop, switch, closure
+    /// or other construct that makes it optional.
+    maybe: bool,
+}
+
+impl Hir {
+    /// Extend the object type of a declaration with the fields
+    /// that are assigned to it after the declaration.
+    ///
+    /// Fields that are not assigned on every path are unioned
+    /// with `()`.
+    pub(super) fn object_shape_from_assignments(
+        &mut self,
+        seen: &mut HashSet<Symbol>,
+        decl: Symbol,
+        ty: Type,
+    ) -> Type {
+        let object = match self.types.get(ty).map(|ty_data| &ty_data.kind) {
+            Some(TypeKind::Object(o)) => o.clone(),
+            _ => return ty,
+        };
+
+        let mut references = match self.symbols.get(decl).unwrap().kind.as_decl() {
+            Some(d) => d.references.iter().copied().collect::<Vec<_>>(),
+            None => return ty,
+        };
+
+        // Keep the fields in source order.
+        references.sort_by_key(|&reference| {
+            self.symbols
+                .get(reference)
+                .unwrap()
+                .source
+                .text_range
+                .map(|range| range.start())
+        });
+
+        let decl_scope = self.symbols.get(decl).unwrap().parent_scope;
+
+        let assignments = references
+            .into_iter()
+            .filter_map(|reference| self.field_assignment(reference, decl_scope))
+            .collect::<Vec<_>>();
+
+        if assignments.is_empty() {
+            return ty;
+        }
+
+        let mut fields = object
+            .fields
+            .iter()
+            .map(|(name, ty)| (name.clone(), vec![*ty]))
+            .collect::<IndexMap<_, _>>();
+
+        for assignment in &assignments {
+            let value_ty = match assignment.value {
+                Some(value) => {
+                    self.resolve_type_for_symbol(seen, value);
+                    self.symbols.get(value).unwrap().ty
+                }
+                None => self.builtin_types.unknown,
+            };
+
+            fields
+                .entry(assignment.name.clone())
+                .or_default()
+                .push(value_ty);
+        }
+
+        let source = self.types.get(ty).unwrap().source;
+
+        let fields = fields
+            .into_iter()
+            .map(|(name, mut types)| {
+                let definite = object.fields.contains_key(&name)
+                    || self.is_field_always_assigned(&name, &assignments);
+
+                if !definite {
+                    types.push(self.builtin_types.void);
+                }
+
+                let ty = self.union_of(source, types);
+                (name, ty)
+            })
+            .collect();
+
+        self.types.insert(TypeData {
+            source,
+            kind: TypeKind::Object(Object { fields }),
+            protected: false,
+        })
+    }
+
+    /// Whether a field is assigned either unconditionally or
+    /// in every branch of an `if` expression with an `else` branch.
+    fn is_field_always_assigned(&self, name: &str, assignments: &[FieldAssignment]) -> bool {
+        let field_assignments = assignments
+            .iter()
+            .filter(|assignment| assignment.name == name && !assignment.maybe)
+            .collect::<Vec<_>>();
+
+        if field_assignments
+            .iter()
+            .any(|assignment| assignment.branches.is_empty())
+        {
+            return true;
+        }
+
+        field_assignments
+            .iter()
+            .filter(|assignment| assignment.branches.len() == 1)
+            .any(|assignment| {
+                let (if_symbol, _) = assignment.branches[0];
+
+                let branches = &self
+                    .symbols
+                    .get(if_symbol)
+                    .unwrap()
+                    .kind
+                    .as_if()
+                    .unwrap()
+                    .branches;
+
+                let has_else = branches.last().map_or(false, |(cond, _)| cond.is_none());
+
+                has_else
+                    && (0..branches.len()).all(|branch_idx| {
+                        field_assignments
+                            .iter()
+                            .any(|a| a.branches == [(if_symbol, branch_idx)])
+                    })
+            })
+    }
+
+    /// Recognise `decl.field = value` and `decl["field"] = value`
+    /// for the given reference to `decl`.
+    fn field_assignment(&self, reference: Symbol, decl_scope: Scope) -> Option<FieldAssignment> {
+        let access_scope = self.symbols.get(reference)?.parent_scope;
+        let access = *self.scopes.get(access_scope)?.parent?.as_symbol()?;
+
+        let name = match &self.symbols.get(access)?.kind {
+            SymbolKind::Binary(binary)
+                if binary.is_field_access() && binary.lhs == Some(reference) =>
+            {
+                self.symbols
+                    .get(binary.rhs?)?
+                    .kind
+                    .as_reference()?
+                    .name
+                    .clone()
+            }
+            _ => {
+                // Index symbols are in the same scope
+                // as their base.
+                let index = self
+                    .scopes
+                    .get(access_scope)?
+                    .symbols
+                    .iter()
+                    .find_map(|&sym| {
+                        let index = self.symbols.get(sym)?.kind.as_index()?;
+                        (index.base == Some(reference)).then(|| (sym, index.index))
+                    });
+
+                match index {
+                    Some((index_symbol, Some(index))) => {
+                        match &self.symbols.get(index)?.kind.as_lit()?.value {
+                            Value::String(s) => {
+                                return self.assignment_of(index_symbol, s.clone(), decl_scope);
+                            }
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+        };
+
+        self.assignment_of(access, name, decl_scope)
+    }
+
+    /// Return the field assignment if `target` is the left-hand side
+    /// of an assignment.
+    fn assignment_of(
+        &self,
+        target: Symbol,
+        name: String,
+        decl_scope: Scope,
+    ) -> Option<FieldAssignment> {
+        let assign_scope = self.symbols.get(target)?.parent_scope;
+        let assignment = *self.scopes.get(assign_scope)?.parent?.as_symbol()?;
+
+        let binary = self.symbols.get(assignment)?.kind.as_binary()?;
+
+        if binary.lookup_text != "=" || binary.lhs != Some(target) {
+            return None;
+        }
+
+        let mut branches = Vec::new();
+        let mut maybe = false;
+
+        let mut scope = self.symbols.get(assignment)?.parent_scope;
+
+        while scope != decl_scope {
+            match self.scopes.get(scope)?.parent? {
+                ScopeParent::Scope(parent_scope) => scope = parent_scope,
+                ScopeParent::Symbol(parent) => {
+                    let parent_data = self.symbols.get(parent)?;
+
+                    match &parent_data.kind {
+                        SymbolKind::Block(_) => {}
+                        SymbolKind::If(if_symbol) => {
+                            let branch_idx = if_symbol
+                                .branches
+                                .iter()
+                                .position(|(_, branch_scope)| *branch_scope == scope)?;
+
+                            branches.push((parent, branch_idx));
+                        }
+                        SymbolKind::Fn(_) => return None,
+                        _ => maybe = true,
+                    }
+
+                    scope = parent_data.parent_scope;
+                }
+            }
+        }
+
+        branches.reverse();
+
+        Some(FieldAssignment {
+            name,
+            value: binary.rhs,
+            branches,
+            maybe,
+        })
+    }
+}
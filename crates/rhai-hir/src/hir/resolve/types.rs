@@ -1,9 +1,11 @@
 use crate::{
     eval::Value,
     hir::BuiltinTypes,
+    scope::ScopeParent,
+    source::SourceInfo,
     symbol::{ReferenceTarget, SymbolKind},
     ty::{Array, Function, Object, Type, TypeData},
-    HashSet, Hir, IndexMap, IndexSet, Symbol, TypeKind,
+    HashSet, Hir, IndexMap, IndexSet, Scope, Symbol, TypeKind,
 };
 use slotmap::SlotMap;
 
@@ -22,21 +24,41 @@ impl Hir {
 
         let mut to_remove = HashSet::with_capacity(symbols.len());
 
+        // The aliased types are resolved first so that
+        // other symbols refer to the final types.
+        for &symbol in &symbols {
+            let alias = match self.symbols.get(symbol).unwrap().kind.as_type_decl() {
+                Some(decl) => decl.ty,
+                None => continue,
+            };
+
+            let visible_types = self.visible_type_aliases(symbol);
+
+            if let Some(TypeKind::Alias(_, alias_ty)) =
+                self.types.get(alias).map(|ty_data| ty_data.kind.clone())
+            {
+                let mut resolved_ty = alias_ty;
+
+                resolve_and_replace(
+                    &mut self.types,
+                    self.builtin_types,
+                    &mut resolved_ty,
+                    &mut to_remove,
+                    &visible_types,
+                );
+
+                // Other aliases might already refer to the aliased type,
+                // so it is updated in place instead of being replaced.
+                if resolved_ty != alias_ty {
+                    let resolved_kind = self.types.get(resolved_ty).unwrap().kind.clone();
+                    self.types.get_mut(alias_ty).unwrap().kind = resolved_kind;
+                    to_remove.remove(&alias_ty);
+                }
+            }
+        }
+
         for symbol in symbols {
-            let visible_types: Vec<_> = self
-                .visible_symbols_from_symbol(symbol)
-                .filter_map(|sym| {
-                    if let Some(decl) = self[sym].kind.as_type_decl() {
-                        if let TypeKind::Alias(name, ty) = &self.types.get(decl.ty).unwrap().kind {
-                            Some((name.clone(), *ty))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            let visible_types = self.visible_type_aliases(symbol);
 
             let symbol_data = self.symbols.get_mut(symbol).unwrap();
 
@@ -105,6 +127,23 @@ impl Hir {
         }
     }
 
+    /// Type aliases visible from the symbol by their names.
+    fn visible_type_aliases(&self, symbol: Symbol) -> Vec<(String, Type)> {
+        self.visible_symbols_from_symbol(symbol)
+            .filter_map(|sym| {
+                if let Some(decl) = self[sym].kind.as_type_decl() {
+                    if let TypeKind::Alias(name, ty) = &self.types.get(decl.ty).unwrap().kind {
+                        Some((name.clone(), *ty))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Resolve and set the type for a symbol.
     ///
     /// Due to references and type-inference this function might
@@ -146,19 +185,61 @@ impl Hir {
                 Some(ReferenceTarget::Symbol(target_sym)) => {
                     self.resolve_type_for_symbol(seen, target_sym);
                     let target_sym_data = self.symbols.get(target_sym).unwrap();
-                    self.symbols.get_mut(symbol).unwrap().ty = target_sym_data.ty;
+
+                    let ty = if target_sym_data.kind.is_decl() {
+                        self.narrowed_reference_type(symbol, target_sym, target_sym_data.ty)
+                    } else {
+                        target_sym_data.ty
+                    };
+
+                    self.symbols.get_mut(symbol).unwrap().ty = ty;
                 }
                 Some(ReferenceTarget::Module(_)) => {
                     sym_data.ty = self.builtin_types.module;
                 }
                 None => sym_data.ty = self.builtin_types.unknown,
             },
+            SymbolKind::Decl(decl) if decl.is_param && decl.ty_decl.is_none() => {
+                let parent_scope = sym_data.parent_scope;
+                let ty = self
+                    .expected_closure_param_type(seen, parent_scope, symbol)
+                    .unwrap_or(self.builtin_types.unknown);
+
+                self.symbols.get_mut(symbol).unwrap().ty = ty;
+            }
+            SymbolKind::Decl(decl) if decl.is_pat && decl.ty_decl.is_none() => {
+                // Loop variables are typed by the `for` symbol
+                // that declares them.
+                let parent_scope = sym_data.parent_scope;
+                let for_symbol = self
+                    .scopes
+                    .get(parent_scope)
+                    .into_iter()
+                    .flat_map(|scope| scope.symbols.iter().copied())
+                    .find(|&sym| {
+                        self.symbols
+                            .get(sym)
+                            .unwrap()
+                            .kind
+                            .as_for()
+                            .map_or(false, |f| f.pat_decls.contains(&symbol))
+                    });
+
+                match for_symbol {
+                    Some(for_symbol) => self.resolve_type_for_symbol(seen, for_symbol),
+                    None => self.symbols.get_mut(symbol).unwrap().ty = self.builtin_types.unknown,
+                }
+            }
             SymbolKind::Decl(decl) => {
                 let ty = if let Some(ty) = decl.ty_decl {
                     ty
                 } else if let Some(val) = decl.value {
                     self.resolve_type_for_symbol(seen, val);
-                    self.symbols.get(val).unwrap().ty
+                    let ty = self.symbols.get(val).unwrap().ty;
+
+                    // Assigned values might refer to the declaration itself.
+                    self.symbols.get_mut(symbol).unwrap().ty = ty;
+                    self.object_shape_from_assignments(seen, symbol, ty)
                 } else {
                     self.builtin_types.unknown
                 };
@@ -269,19 +350,8 @@ impl Hir {
 
                 let ret = if is_def {
                     ret_ty
-                } else if let Some(last_expr) = self
-                    .scopes
-                    .get(scope)
-                    .unwrap()
-                    .symbols
-                    .iter()
-                    .copied()
-                    .find(|&sym| !self.symbols.get(sym).unwrap().is_param())
-                {
-                    self.resolve_type_for_symbol(seen, last_expr);
-                    self.symbols.get(last_expr).unwrap().ty
                 } else {
-                    self.builtin_types.unknown
+                    self.resolve_return_type(seen, source, scope)
                 };
 
                 self.symbols.get_mut(symbol).unwrap().ty = self.types.insert(TypeData {
@@ -321,20 +391,7 @@ impl Hir {
                     .map(|(name, sym)| (name, self.symbols.get(sym).unwrap().ty))
                     .collect::<Vec<_>>();
 
-                let ret = if let Some(last_expr) = self
-                    .scopes
-                    .get(scope)
-                    .unwrap()
-                    .symbols
-                    .iter()
-                    .copied()
-                    .find(|&sym| !self.symbols.get(sym).unwrap().is_param())
-                {
-                    self.resolve_type_for_symbol(seen, last_expr);
-                    self.symbols.get(last_expr).unwrap().ty
-                } else {
-                    self.builtin_types.unknown
-                };
+                let ret = self.resolve_return_type(seen, source, scope);
 
                 self.symbols.get_mut(symbol).unwrap().ty = self.types.insert(TypeData {
                     source,
@@ -445,7 +502,7 @@ impl Hir {
                         self.resolve_type_for_symbol(seen, lhs);
                         lhs
                     })
-                    .and_then(|lhs| self[self[lhs].ty].kind.as_object())
+                    .and_then(|lhs| self[self.follow_aliases(self[lhs].ty)].kind.as_object())
                     .and_then(|object| Some((object, rhs.and_then(|rhs| self[rhs].name(self))?)))
                     .and_then(|(object, field_name)| object.fields.get(field_name))
                     .copied()
@@ -545,9 +602,26 @@ impl Hir {
             | SymbolKind::TypeDecl(_) => {
                 sym_data.ty = self.builtin_types.never;
             }
+            SymbolKind::For(f) => {
+                let pat_decls = f.pat_decls.clone();
+
+                let item_ty = match f.iterable {
+                    Some(iterable) => self.resolve_iterable_item_type(seen, iterable),
+                    None => self.builtin_types.unknown,
+                };
+
+                // `for (item, index) in ...`
+                let pat_types = [item_ty, self.builtin_types.int];
+
+                for (decl, ty) in pat_decls.into_iter().zip(pat_types) {
+                    seen.insert(decl);
+                    self.symbols.get_mut(decl).unwrap().ty = ty;
+                }
+
+                self.symbols.get_mut(symbol).unwrap().ty = self.builtin_types.void;
+            }
             SymbolKind::Import(_)
             | SymbolKind::Export(_)
-            | SymbolKind::For(_)
             | SymbolKind::Loop(_)
             | SymbolKind::While(_) => {
                 sym_data.ty = self.builtin_types.void;
@@ -556,6 +630,285 @@ impl Hir {
     }
 }
 
+impl Hir {
+    /// Determine the return type of a function or closure
+    /// from its tail expression and all `return` statements in its body.
+    fn resolve_return_type(
+        &mut self,
+        seen: &mut HashSet<Symbol>,
+        source: SourceInfo,
+        fn_scope: Scope,
+    ) -> Type {
+        let tail = self
+            .scopes
+            .get(fn_scope)
+            .unwrap()
+            .symbols
+            .iter()
+            .rev()
+            .copied()
+            .find(|&sym| !self.symbols.get(sym).unwrap().is_param());
+
+        let return_exprs = self
+            .descendant_symbols(fn_scope)
+            .filter(|&sym| self.enclosing_fn_scope(sym) == Some(fn_scope))
+            .filter_map(|sym| Some(self.symbols.get(sym).unwrap().kind.as_return()?.expr))
+            .collect::<Vec<_>>();
+
+        let mut ret_types = Vec::with_capacity(return_exprs.len() + 1);
+
+        for expr in return_exprs {
+            match expr {
+                Some(expr) => {
+                    self.resolve_type_for_symbol(seen, expr);
+                    ret_types.push(self.symbols.get(expr).unwrap().ty);
+                }
+                None => ret_types.push(self.builtin_types.void),
+            }
+        }
+
+        match tail {
+            Some(tail) => {
+                self.resolve_type_for_symbol(seen, tail);
+                let tail_data = self.symbols.get(tail).unwrap();
+
+                // A trailing declaration is a statement.
+                ret_types.push(if tail_data.kind.is_decl() {
+                    self.builtin_types.void
+                } else {
+                    tail_data.ty
+                });
+            }
+            None => ret_types.push(self.builtin_types.void),
+        }
+
+        self.union_of(source, ret_types)
+    }
+
+    /// The scope of the innermost function or closure
+    /// the symbol is part of.
+    fn enclosing_fn_scope(&self, symbol: Symbol) -> Option<Scope> {
+        let mut scope = self.symbols.get(symbol)?.parent_scope;
+
+        loop {
+            match self.scopes.get(scope)?.parent? {
+                ScopeParent::Scope(parent_scope) => scope = parent_scope,
+                ScopeParent::Symbol(parent_symbol) => {
+                    let parent_data = self.symbols.get(parent_symbol)?;
+
+                    if parent_data.kind.is_fn() || parent_data.kind.is_closure() {
+                        return Some(scope);
+                    }
+
+                    scope = parent_data.parent_scope;
+                }
+            }
+        }
+    }
+
+    /// Create a union of the given types.
+    ///
+    /// Nested unions are flattened, duplicates and the never type
+    /// are omitted. If no types remain, the never type is returned.
+    pub(super) fn union_of(&mut self, source: SourceInfo, types: Vec<Type>) -> Type {
+        let mut members: Vec<Type> = Vec::with_capacity(types.len());
+
+        let types = types
+            .into_iter()
+            .flat_map(|ty| match &self.types.get(ty).unwrap().kind {
+                TypeKind::Union(tys) => tys.iter().copied().collect(),
+                _ => vec![ty],
+            })
+            .collect::<Vec<_>>();
+
+        for ty in types {
+            if self.types.get(ty).unwrap().kind.is_never() {
+                continue;
+            }
+
+            if !members.iter().any(|member| member.is(self, ty, true)) {
+                members.push(ty);
+            }
+        }
+
+        match members.len() {
+            0 => self.builtin_types.never,
+            1 => members[0],
+            _ => self.types.insert(TypeData {
+                source,
+                kind: TypeKind::Union(members.into_iter().collect()),
+                protected: false,
+            }),
+        }
+    }
+
+    /// Determine the type of a closure parameter from the
+    /// function signature the closure is passed to, e.g. `x` is a `String`
+    /// in `arr.filter(|x| ...)` if `filter` is declared
+    /// as `fn filter(array: [String], f: Fn(String) -> bool)`.
+    fn expected_closure_param_type(
+        &mut self,
+        seen: &mut HashSet<Symbol>,
+        closure_scope: Scope,
+        param: Symbol,
+    ) -> Option<Type> {
+        let closure = *self.scopes.get(closure_scope)?.parent?.as_symbol()?;
+
+        if !self.symbols.get(closure)?.kind.is_closure() {
+            return None;
+        }
+
+        let param_idx = self
+            .scopes
+            .get(closure_scope)?
+            .symbols
+            .iter()
+            .position(|&sym| sym == param)?;
+
+        let call_scope = self.symbols.get(closure)?.parent_scope;
+
+        let (call, arg_idx) = self
+            .scopes
+            .get(call_scope)?
+            .symbols
+            .iter()
+            .find_map(|&sym| {
+                let call = self.symbols.get(sym)?.kind.as_call()?;
+                let arg_idx = call.arguments.iter().position(|&arg| arg == closure)?;
+                Some((sym, arg_idx))
+            })?;
+
+        let expected_ty = self.expected_argument_type(seen, call, arg_idx)?;
+
+        let expected_fn = self
+            .types
+            .get(self.follow_aliases(expected_ty))?
+            .kind
+            .as_fn()?;
+
+        expected_fn.params.get(param_idx).map(|(_, ty)| *ty)
+    }
+
+    /// Determine the declared type of an argument of a call.
+    ///
+    /// Method-style calls (`a.f(b)`) are looked up by name, the receiver
+    /// is the first parameter of the function.
+    fn expected_argument_type(
+        &mut self,
+        seen: &mut HashSet<Symbol>,
+        call: Symbol,
+        arg_idx: usize,
+    ) -> Option<Type> {
+        let lhs = self.symbols.get(call)?.kind.as_call()?.lhs?;
+
+        self.resolve_type_for_symbol(seen, lhs);
+
+        if let Some(f) = self
+            .types
+            .get(self.follow_aliases(self.symbols.get(lhs)?.ty))?
+            .kind
+            .as_fn()
+        {
+            return f.params.get(arg_idx).map(|(_, ty)| *ty);
+        }
+
+        let method_call = self
+            .symbols
+            .get(lhs)?
+            .kind
+            .as_binary()
+            .filter(|b| b.is_field_access())?;
+
+        let (receiver, method) = (method_call.lhs?, method_call.rhs?);
+
+        let fn_name = self.symbols.get(method)?.kind.as_reference()?.name.clone();
+
+        self.resolve_type_for_symbol(seen, receiver);
+        let receiver_ty = self.symbols.get(receiver)?.ty;
+
+        let candidates = self
+            .visible_symbols_from_symbol(call)
+            .filter(|&sym| {
+                self.symbols
+                    .get(sym)
+                    .and_then(|data| data.kind.as_fn())
+                    .map_or(false, |f| f.name == fn_name)
+            })
+            .collect::<Vec<_>>();
+
+        for candidate in candidates {
+            self.resolve_type_for_symbol(seen, candidate);
+
+            let candidate_ty = self.symbols.get(candidate)?.ty;
+
+            if let Some(f) = self.types.get(candidate_ty)?.kind.as_fn() {
+                let receiver_matches = f
+                    .params
+                    .first()
+                    .map_or(false, |(_, ty)| ty.is(self, receiver_ty, false));
+
+                if receiver_matches {
+                    if let Some((_, ty)) = f.params.get(arg_idx + 1) {
+                        return Some(*ty);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Follow aliases to the aliased type.
+    ///
+    /// Aliases are followed a limited amount of times
+    /// in case of self-referencing types.
+    #[must_use]
+    pub fn follow_aliases(&self, mut ty: Type) -> Type {
+        for _ in 0..16 {
+            match self.types.get(ty).map(|ty_data| &ty_data.kind) {
+                Some(TypeKind::Alias(_, alias_ty)) => ty = *alias_ty,
+                _ => break,
+            }
+        }
+
+        ty
+    }
+
+    /// Determine the type of the items produced by iterating
+    /// over the given symbol in a `for` loop.
+    fn resolve_iterable_item_type(&mut self, seen: &mut HashSet<Symbol>, iterable: Symbol) -> Type {
+        let range_operands = self
+            .symbols
+            .get(iterable)
+            .unwrap()
+            .kind
+            .as_binary()
+            .filter(|binary| binary.lookup_text == ".." || binary.lookup_text == "..=")
+            .map(|binary| [binary.lhs, binary.rhs]);
+
+        if let Some(operands) = range_operands {
+            for operand in operands.into_iter().flatten() {
+                self.resolve_type_for_symbol(seen, operand);
+            }
+
+            seen.insert(iterable);
+            self.symbols.get_mut(iterable).unwrap().ty = self.builtin_types.int;
+            return self.builtin_types.int;
+        }
+
+        self.resolve_type_for_symbol(seen, iterable);
+
+        let ty = self.follow_aliases(self.symbols.get(iterable).unwrap().ty);
+
+        match &self.types.get(ty).unwrap().kind {
+            TypeKind::Array(arr) => arr.items,
+            TypeKind::Iterator(items) => *items,
+            TypeKind::String => self.builtin_types.char,
+            _ => self.builtin_types.unknown,
+        }
+    }
+}
+
 fn resolve_and_replace(
     types: &mut SlotMap<Type, TypeData>,
     builtin_types: BuiltinTypes,
@@ -564,6 +917,63 @@ fn resolve_and_replace(
     visible_types: &[(String, Type)],
 ) {
     if let Some(ty_data) = types.get(*ty) {
+        // Nested types are resolved in place, aliases are not
+        // followed to avoid recursion with self-referencing types.
+        match ty_data.kind.clone() {
+            TypeKind::Array(Array { mut items }) => {
+                resolve_and_replace(types, builtin_types, &mut items, to_remove, visible_types);
+                types.get_mut(*ty).unwrap().kind = TypeKind::Array(Array { items });
+                return;
+            }
+            TypeKind::Iterator(mut items) => {
+                resolve_and_replace(types, builtin_types, &mut items, to_remove, visible_types);
+                types.get_mut(*ty).unwrap().kind = TypeKind::Iterator(items);
+                return;
+            }
+            TypeKind::Fn(mut f) => {
+                for (_, param_ty) in &mut f.params {
+                    resolve_and_replace(types, builtin_types, param_ty, to_remove, visible_types);
+                }
+                resolve_and_replace(types, builtin_types, &mut f.ret, to_remove, visible_types);
+                types.get_mut(*ty).unwrap().kind = TypeKind::Fn(f);
+                return;
+            }
+            TypeKind::Tuple(mut tys) => {
+                for item_ty in &mut tys {
+                    resolve_and_replace(types, builtin_types, item_ty, to_remove, visible_types);
+                }
+                types.get_mut(*ty).unwrap().kind = TypeKind::Tuple(tys);
+                return;
+            }
+            TypeKind::Object(mut o) => {
+                for field_ty in o.fields.values_mut() {
+                    resolve_and_replace(types, builtin_types, field_ty, to_remove, visible_types);
+                }
+                types.get_mut(*ty).unwrap().kind = TypeKind::Object(o);
+                return;
+            }
+            TypeKind::Union(tys) => {
+                let tys = tys
+                    .into_iter()
+                    .map(|mut member_ty| {
+                        resolve_and_replace(
+                            types,
+                            builtin_types,
+                            &mut member_ty,
+                            to_remove,
+                            visible_types,
+                        );
+                        member_ty
+                    })
+                    .collect();
+                types.get_mut(*ty).unwrap().kind = TypeKind::Union(tys);
+                return;
+            }
+            _ => {}
+        }
+
+        let ty_data = types.get(*ty).unwrap();
+
         if let TypeKind::Unresolved(r) = &ty_data.kind {
             match r.trim() {
                 "module" => {
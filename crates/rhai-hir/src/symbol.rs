@@ -775,6 +775,38 @@ impl BinarySymbol {
     pub fn is_field_access(&self) -> bool {
         self.lookup_text == "."
     }
+
+    /// Returns `true` for both simple (`=`) and
+    /// compound (e.g. `+=`) assignments.
+    #[must_use]
+    pub fn is_assignment(&self) -> bool {
+        matches!(
+            self.op,
+            Some(BinaryOpKind::Regular(
+                SyntaxKind::OP_ASSIGN
+                    | SyntaxKind::OP_ADD_ASSIGN
+                    | SyntaxKind::OP_SUB_ASSIGN
+                    | SyntaxKind::OP_MUL_ASSIGN
+                    | SyntaxKind::OP_DIV_ASSIGN
+                    | SyntaxKind::OP_MOD_ASSIGN
+                    | SyntaxKind::OP_POW_ASSIGN
+                    | SyntaxKind::OP_SHIFT_RIGHT_ASSIGN
+                    | SyntaxKind::OP_SHIFT_LEFT_ASSIGN
+                    | SyntaxKind::OP_AND_ASSIGN
+                    | SyntaxKind::OP_OR_ASSIGN
+                    | SyntaxKind::OP_XOR_ASSIGN
+            ))
+        )
+    }
+}
+
+/// Whether an occurrence of a symbol reads or writes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolAccess {
+    Read,
+    /// A declaration or the left-hand side
+    /// of an assignment.
+    Write,
 }
 
 #[derive(Debug, Clone)]
@@ -880,6 +912,9 @@ pub struct LoopSymbol {
 #[derive(Debug, Default, Clone)]
 pub struct ForSymbol {
     pub iterable: Option<Symbol>,
+    /// Declarations introduced by the loop pattern,
+    /// e.g. `x` and `i` in `for (x, i) in arr {}`.
+    pub pat_decls: Vec<Symbol>,
     pub scope: Scope,
 }
 
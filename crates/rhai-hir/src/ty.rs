@@ -28,6 +28,9 @@ impl Type {
             (TypeKind::Unresolved(ty1), TypeKind::Unresolved(ty2)) => ty1 == ty2,
             (TypeKind::Alias(_, ty1), TypeKind::Alias(_, ty2)) => ty1.is(hir, *ty2, true),
             (TypeKind::Array(arr1), TypeKind::Array(arr2)) => arr1.items.is(hir, arr2.items, true),
+            (TypeKind::Iterator(items1), TypeKind::Iterator(items2)) => {
+                items1.is(hir, *items2, true)
+            }
             (TypeKind::Fn(f1), TypeKind::Fn(f2)) => {
                 f1.params.len() == f2.params.len()
                     && f1
@@ -103,6 +106,9 @@ impl core::fmt::Display for TypeFormatter<'_> {
                 write!(f, "{}", arr.items.fmt(self.hir))?;
                 f.write_str("]")?;
             }
+            TypeKind::Iterator(items) => {
+                write!(f, "Iterator<{}>", items.fmt(self.hir))?;
+            }
             TypeKind::Object(obj) => {
                 f.write_str("#{")?;
 
@@ -143,7 +149,11 @@ impl core::fmt::Display for TypeFormatter<'_> {
                     }
                     first = false;
 
-                    write!(f, "{name}: {}", ty.fmt(self.hir))?;
+                    if name.is_empty() {
+                        write!(f, "{}", ty.fmt(self.hir))?;
+                    } else {
+                        write!(f, "{name}: {}", ty.fmt(self.hir))?;
+                    }
                 }
 
                 if func.is_closure {
@@ -182,6 +192,9 @@ pub enum TypeKind {
     Alias(String, Type),
     Unresolved(String),
     Tuple(Vec<Type>),
+    /// An iterable value with the given item type,
+    /// declared as `Iterator<T>` in definitions.
+    Iterator(Type),
     /// An arbitrary atomic primitive type.
     Primitive(String),
     Never,
@@ -369,6 +382,23 @@ impl TypeKind {
             None
         }
     }
+
+    /// Returns `true` if the type kind is [`Iterator`].
+    ///
+    /// [`Iterator`]: TypeKind::Iterator
+    #[must_use]
+    pub fn is_iterator(&self) -> bool {
+        matches!(self, Self::Iterator(..))
+    }
+
+    #[must_use]
+    pub fn as_iterator(&self) -> Option<&Type> {
+        if let Self::Iterator(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for TypeKind {
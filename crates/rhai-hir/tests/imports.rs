@@ -1,4 +1,4 @@
-use rhai_hir::Hir;
+use rhai_hir::{error::ErrorKind, Hir};
 use rhai_rowan::parser::Parser;
 
 #[test]
@@ -83,3 +83,119 @@ import "./foo.rhai" as foo;
 
     assert_eq!(hir.missing_modules().len(), 1);
 }
+
+#[test]
+fn test_unresolved_import_url() {
+    let root_src = r#"
+import "./missing.rhai" as m;
+"#;
+
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///dir/root.rhai".parse().unwrap(),
+        &Parser::new(root_src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    let errors = hir.errors();
+
+    assert_eq!(errors.len(), 1);
+
+    match &errors[0].kind {
+        ErrorKind::UnresolvedImport { import_url, .. } => {
+            assert_eq!(import_url, &Some("test:///dir/missing.rhai".parse().unwrap()));
+        }
+        kind => panic!("unexpected error: {kind}"),
+    }
+}
+
+#[test]
+fn test_rename_source() {
+    let root_src = r#"
+import "./module.rhai" as m;
+
+m::x;
+"#;
+
+    let module_src = r#"
+export const x = 1;
+"#;
+
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(root_src).parse_script().into_syntax(),
+    );
+    hir.add_source(
+        &"test:///module.rhai".parse().unwrap(),
+        &Parser::new(module_src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+    assert!(hir.errors().is_empty());
+
+    let module_source = hir
+        .source_of(&"test:///module.rhai".parse().unwrap())
+        .unwrap();
+    assert!(hir.rename_source(module_source, &"test:///sub/module.rhai".parse().unwrap()));
+    assert!(hir
+        .source_of(&"test:///module.rhai".parse().unwrap())
+        .is_none());
+
+    hir.resolve_all();
+    assert!(hir
+        .errors()
+        .iter()
+        .any(|e| matches!(e.kind, ErrorKind::UnresolvedImport { .. })));
+
+    let root_src = r#"
+import "./sub/module.rhai" as m;
+
+m::x;
+"#;
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(root_src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+    assert!(hir.errors().is_empty());
+}
+
+#[test]
+fn test_import_alias_references() {
+    let root_src = r#"
+import "./module.rhai" as m;
+
+m::x;
+m::x;
+"#;
+
+    let module_src = r#"
+export const x = 1;
+"#;
+
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(root_src).parse_script().into_syntax(),
+    );
+    hir.add_source(
+        &"test:///module.rhai".parse().unwrap(),
+        &Parser::new(module_src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    let alias = hir
+        .symbols()
+        .find_map(|(_, data)| data.kind.as_decl().filter(|decl| decl.name == "m"))
+        .unwrap();
+
+    assert_eq!(alias.references.len(), 2);
+}
@@ -0,0 +1,43 @@
+use rhai_hir::Hir;
+use rhai_rowan::parser::Parser;
+
+#[test]
+fn test_source_symbols_index() {
+    let mut hir = Hir::new();
+
+    let root_url = "test:///root.rhai".parse().unwrap();
+    let other_url = "test:///other.rhai".parse().unwrap();
+
+    hir.add_source(
+        &root_url,
+        &Parser::new("let a = 1; let b = a + 2;")
+            .parse_script()
+            .into_syntax(),
+    );
+    hir.add_source(
+        &other_url,
+        &Parser::new("fn foo() { 3 }").parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    let root = hir.source_of(&root_url).unwrap();
+
+    let mut indexed = hir.source_symbols(root).map(|(s, _)| s).collect::<Vec<_>>();
+    let mut scanned = hir
+        .symbols()
+        .filter(|(_, data)| data.source.is(root))
+        .map(|(s, _)| s)
+        .collect::<Vec<_>>();
+
+    indexed.sort();
+    scanned.sort();
+
+    assert!(!indexed.is_empty());
+    assert_eq!(indexed, scanned);
+
+    hir.remove_source(root);
+
+    assert_eq!(hir.source_symbols(root).count(), 0);
+    assert!(hir.symbols().all(|(_, data)| !data.source.is(root)));
+}
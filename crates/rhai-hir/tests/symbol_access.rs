@@ -0,0 +1,54 @@
+use rhai_hir::{symbol::SymbolAccess, Hir};
+use rhai_rowan::parser::Parser;
+
+#[test]
+fn test_reference_access() {
+    let src = r#"
+let a = 1;
+a = 2;
+a += 3;
+let b = a + 1;
+b.foo = a;
+"#;
+
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    let (decl, _) = hir
+        .symbols()
+        .find(|(_, data)| data.kind.as_decl().map_or(false, |d| d.name == "a"))
+        .unwrap();
+
+    let mut accesses = hir[decl]
+        .kind
+        .as_decl()
+        .unwrap()
+        .references
+        .iter()
+        .map(|&r| {
+            (
+                hir[r].source.text_range.unwrap().start(),
+                hir.symbol_access(r),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    accesses.sort_by_key(|(start, _)| *start);
+
+    assert_eq!(hir.symbol_access(decl), SymbolAccess::Write);
+    assert_eq!(
+        accesses.into_iter().map(|(_, a)| a).collect::<Vec<_>>(),
+        [
+            SymbolAccess::Write,
+            SymbolAccess::Write,
+            SymbolAccess::Read,
+            SymbolAccess::Read
+        ]
+    );
+}
@@ -0,0 +1,471 @@
+use rhai_hir::Hir;
+use rhai_rowan::parser::Parser;
+
+fn script_hir(src: &str) -> Hir {
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(src).parse_script().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    hir
+}
+
+/// Returns the formatted type of the last declaration with the given name.
+fn decl_type(hir: &Hir, name: &str) -> String {
+    let (_, data) = hir
+        .symbols()
+        .filter(|(_, data)| data.kind.as_decl().map_or(false, |decl| decl.name == name))
+        .last()
+        .unwrap();
+
+    data.ty.fmt(hir).to_string()
+}
+
+#[test]
+fn test_for_range() {
+    let hir = script_hir(
+        r#"
+let n = 5;
+for i in 0..10 {}
+for j in 0..=n {}
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "i"), "int");
+    assert_eq!(decl_type(&hir, "j"), "int");
+}
+
+#[test]
+fn test_for_array_and_string() {
+    let hir = script_hir(
+        r#"
+let arr = [1, 2, 3];
+for x in arr {}
+for c in "hello" {}
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "x"), "int");
+    assert_eq!(decl_type(&hir, "c"), "char");
+}
+
+#[test]
+fn test_for_tuple_pattern() {
+    let hir = script_hir(
+        r#"
+for (s, i) in ["a", "b"] {}
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "s"), "String");
+    assert_eq!(decl_type(&hir, "i"), "int");
+}
+
+#[test]
+fn test_for_iterator_definition() {
+    let root_src = r#"
+for x in range(0, 10) {}
+"#;
+
+    let static_src = r#"
+module static;
+
+fn range(from: int, to: int) -> Iterator<int>;
+"#;
+
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(root_src).parse_script().into_syntax(),
+    );
+    hir.add_source(
+        &"test:///static.d.rhai".parse().unwrap(),
+        &Parser::new(static_src).parse_def().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    assert_eq!(decl_type(&hir, "x"), "int");
+}
+
+/// Returns the formatted return type of the function with the given name.
+fn fn_ret_type(hir: &Hir, name: &str) -> String {
+    let (_, data) = hir
+        .symbols()
+        .find(|(_, data)| data.kind.as_fn().map_or(false, |f| f.name == name))
+        .unwrap();
+
+    hir[data.ty].kind.as_fn().unwrap().ret.fmt(hir).to_string()
+}
+
+#[test]
+fn test_return_paths() {
+    let hir = script_hir(
+        r#"
+fn foo(a) {
+    if a {
+        return "a";
+    }
+
+    for i in 0..10 {
+        if i == 5 {
+            return 5;
+        }
+    }
+
+    "b"
+}
+"#,
+    );
+
+    let mut ret_types = fn_ret_type(&hir, "foo")
+        .split(" | ")
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    ret_types.sort();
+
+    assert_eq!(ret_types, ["String", "int"]);
+}
+
+#[test]
+fn test_return_ignores_closures() {
+    let hir = script_hir(
+        r#"
+fn foo() {
+    let f = |x| { return 2; };
+    let y = 1;
+}
+"#,
+    );
+
+    assert_eq!(fn_ret_type(&hir, "foo"), "()");
+}
+
+#[test]
+fn test_return_always_throws() {
+    let hir = script_hir(
+        r#"
+fn foo() {
+    throw "error";
+}
+"#,
+    );
+
+    assert_eq!(fn_ret_type(&hir, "foo"), "!");
+}
+
+fn script_hir_with_def(src: &str, def_src: &str) -> Hir {
+    let mut hir = Hir::new();
+
+    hir.add_source(
+        &"test:///root.rhai".parse().unwrap(),
+        &Parser::new(src).parse_script().into_syntax(),
+    );
+    hir.add_source(
+        &"test:///static.d.rhai".parse().unwrap(),
+        &Parser::new(def_src).parse_def().into_syntax(),
+    );
+
+    hir.resolve_all();
+
+    hir
+}
+
+#[test]
+fn test_closure_param_from_call() {
+    let hir = script_hir_with_def(
+        r#"
+each(|n| n + 1);
+"#,
+        r#"
+module static;
+
+fn each(f: Fn(int) -> int);
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "n"), "int");
+}
+
+#[test]
+fn test_closure_param_from_method_call() {
+    let hir = script_hir_with_def(
+        r#"
+let arr = ["a", "b"];
+let filtered = arr.filter(|x, i| x.len() > 0);
+"#,
+        r#"
+module static;
+
+fn filter(array: [String], f: Fn(String, int) -> bool) -> [String];
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "x"), "String");
+    assert_eq!(decl_type(&hir, "i"), "int");
+}
+
+/// Returns the formatted types of all references with the given name
+/// in source order.
+fn reference_types(hir: &Hir, name: &str) -> Vec<String> {
+    let mut refs = hir
+        .symbols()
+        .filter(|(_, data)| data.kind.as_reference().map_or(false, |r| r.name == name))
+        .map(|(_, data)| (data.source.text_range.unwrap().start(), data.ty))
+        .collect::<Vec<_>>();
+
+    refs.sort_by_key(|(start, _)| *start);
+
+    refs.into_iter()
+        .map(|(_, ty)| ty.fmt(hir).to_string())
+        .collect()
+}
+
+#[test]
+fn test_narrowing_if_type_of() {
+    let hir = script_hir(
+        r#"
+let x = if true { "a" } else { 1 };
+
+if type_of(x) == "string" {
+    x;
+} else {
+    x;
+}
+"#,
+    );
+
+    assert_eq!(
+        reference_types(&hir, "x"),
+        ["String | int", "String", "int"]
+    );
+}
+
+#[test]
+fn test_narrowing_is_method() {
+    let hir = script_hir(
+        r#"
+fn foo(x) {
+    if x.is_string() && true {
+        x
+    }
+}
+"#,
+    );
+
+    assert_eq!(reference_types(&hir, "x"), ["?", "String"]);
+}
+
+#[test]
+fn test_switch_discard_arm() {
+    let hir = script_hir(
+        r#"
+switch 1 {
+    1 => "one",
+    _ => "other",
+}
+"#,
+    );
+
+    let (_, switch) = hir
+        .symbols()
+        .find_map(|(symbol, data)| Some((symbol, data.kind.as_switch()?)))
+        .unwrap();
+
+    assert_eq!(switch.arms.len(), 2);
+
+    let discard_arm = &switch.arms[1];
+    assert!(hir[discard_arm.pat_expr.unwrap()].kind.is_discard());
+    assert!(hir[discard_arm.value_expr.unwrap()].kind.is_lit());
+
+    // The value of the discard arm is only added once.
+    let lit_count = hir
+        .symbols()
+        .filter(|(_, data)| data.kind.is_lit())
+        .count();
+    assert_eq!(lit_count, 4);
+}
+
+#[test]
+fn test_narrowing_switch_type_of() {
+    let hir = script_hir(
+        r#"
+fn foo(x) {
+    switch type_of(x) {
+        "i64" => x,
+        "array" => { x },
+        _ => x,
+    }
+}
+"#,
+    );
+
+    assert_eq!(reference_types(&hir, "x"), ["?", "int", "[?]", "?"]);
+}
+
+#[test]
+fn test_narrowing_switch_arm_expression() {
+    let hir = script_hir(
+        r#"
+fn foo(x) {
+    switch type_of(x) {
+        "string" => x.len(),
+        "array" => x + [1],
+        _ => x,
+    }
+}
+"#,
+    );
+
+    assert_eq!(reference_types(&hir, "x"), ["?", "String", "[?]", "?"]);
+}
+
+#[test]
+fn test_narrowing_reload_keeps_type_count() {
+    let src = r#"
+fn foo(x) {
+    if type_of(x) == "array" {
+        x
+    } else if x.is_map() {
+        x
+    }
+}
+
+let y = if true { 1 } else if false { "a" } else { [1] };
+
+if type_of(y) == "int" {
+    y;
+} else {
+    y;
+}
+"#;
+
+    let url = "test:///root.rhai".parse().unwrap();
+    let mut hir = script_hir(src);
+    let type_count = hir.types().count();
+
+    for _ in 0..3 {
+        hir.add_source(&url, &Parser::new(src).parse_script().into_syntax());
+        hir.resolve_all();
+
+        assert_eq!(hir.types().count(), type_count);
+    }
+}
+
+#[test]
+fn test_object_field_assignments() {
+    let hir = script_hir(
+        r#"
+let m = #{ id: 1 };
+m.name = "x";
+m["age"] = 3;
+"#,
+    );
+
+    assert_eq!(decl_type(&hir, "m"), "#{id: int, name: String, age: int}");
+}
+
+#[test]
+fn test_object_conditional_field_assignments() {
+    let hir = script_hir(
+        r#"
+let m = #{};
+
+if true {
+    m.a = 1;
+    m.b = 1;
+} else {
+    m.a = 2;
+}
+
+for i in 0..10 {
+    m.c = "c";
+}
+"#,
+    );
+
+    assert_eq!(
+        decl_type(&hir, "m"),
+        "#{a: int, b: int | (), c: String | ()}"
+    );
+}
+
+/// Returns the names of the type declarations of the given type.
+fn type_decl_names(hir: &Hir, ty: rhai_hir::ty::Type) -> Vec<String> {
+    hir.type_decls_of(ty)
+        .into_iter()
+        .map(|decl| {
+            let alias = hir[decl].kind.as_type_decl().unwrap().ty;
+            alias.fmt(hir).to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_type_decls_of_alias() {
+    let hir = script_hir_with_def(
+        r#"
+let x = foo;
+let y = x.bar;
+"#,
+        r#"
+module static;
+
+type Foo = #{ bar: Bar };
+type Bar = int;
+
+const foo: Foo;
+"#,
+    );
+
+    let decl_ty = |name: &str| {
+        hir.symbols()
+            .find(|(_, data)| data.kind.as_decl().map_or(false, |d| d.name == name))
+            .unwrap()
+            .1
+            .ty
+    };
+
+    assert_eq!(type_decl_names(&hir, decl_ty("x")), ["Foo"]);
+    assert_eq!(type_decl_names(&hir, decl_ty("y")), ["Bar"]);
+}
+
+#[test]
+fn test_object_field_accesses() {
+    let hir = script_hir(
+        r#"
+let obj = #{ a: 1 };
+obj.b = 2;
+let x = obj.a + obj.b;
+let other = #{ a: 1 };
+other.a;
+"#,
+    );
+
+    let literal = |name: &str| {
+        let (_, data) = hir
+            .symbols()
+            .find(|(_, data)| data.kind.as_decl().map_or(false, |decl| decl.name == name))
+            .unwrap();
+
+        hir.object_literal_of(data.ty).unwrap()
+    };
+
+    let obj = literal("obj");
+    let other = literal("other");
+
+    assert_ne!(obj, other);
+    assert!(hir[obj].kind.is_object());
+
+    let accesses = hir.object_field_accesses(obj, "a").collect::<Vec<_>>();
+    assert_eq!(accesses.len(), 1);
+    assert!(hir.field_access_of(accesses[0]).is_some());
+
+    assert_eq!(hir.object_field_accesses(obj, "b").count(), 2);
+    assert_eq!(hir.object_field_accesses(other, "a").count(), 1);
+}
@@ -1,17 +1,41 @@
 use rhai_common::environment::Environment;
 use crate::{
+    lsp_ext,
     world::{Document, World},
 };
+use anyhow::Context as AnyhowContext;
 use lsp_async_stub::{util::LspExt, Context, RequestWriter};
 use lsp_types::{
     notification, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
     PublishDiagnosticsParams, Url,
 };
 use rhai_hir::{error::ErrorKind, Hir};
+use std::sync::atomic::Ordering;
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn publish_all_diagnostics<E: Environment>(context: Context<World<E>>) {
-    let workspaces = context.workspaces.read().await;
+pub(crate) async fn publish_all_diagnostics<E: Environment>(mut context: Context<World<E>>) {
+    context.diagnostics_changed.notify_waiters();
+
+    if context.pull_diagnostics.load(Ordering::SeqCst) {
+        if !context.client_supports("/workspace/diagnostics/refreshSupport") {
+            return;
+        }
+
+        // Changes in one document can affect the diagnostics of others,
+        // so the client has to pull them again.
+        if let Err(error) = context
+            .write_request::<lsp_ext::request::WorkspaceDiagnosticRefresh, _>(None)
+            .await
+            .context("failed to refresh diagnostics")
+            .and_then(|res| res.into_result().context("invalid refresh response"))
+        {
+            tracing::debug!(?error, "diagnostics were not refreshed");
+        }
+
+        return;
+    }
+
+    let workspaces = context.workspaces.snapshot();
     let document_urls = workspaces
         .iter()
         .flat_map(|(_, ws)| ws.documents.keys().cloned())
@@ -31,9 +55,11 @@ pub(crate) async fn publish_diagnostics<E: Environment>(
     mut context: Context<World<E>>,
     document_url: Url,
 ) {
-    let mut diags = Vec::new();
+    if context.pull_diagnostics.load(Ordering::SeqCst) {
+        return;
+    }
 
-    let workspaces = context.workspaces.read().await;
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&document_url);
 
     let doc = match ws.documents.get(&document_url) {
@@ -41,45 +67,25 @@ pub(crate) async fn publish_diagnostics<E: Environment>(
         None => return,
     };
 
-    collect_syntax_errors(doc, &mut diags);
+    let diags = document_diagnostics(&document_url, doc, &ws.hir);
+    let version = doc.version;
     drop(workspaces);
 
+    // The diagnostics are out of date, they are
+    // published again once the changes are applied.
+    if context.pending_changes.contains(&document_url) {
+        tracing::debug!(%document_url, ?version, "discarding outdated diagnostics");
+        return;
+    }
+
     context
         .write_notification::<notification::PublishDiagnostics, _>(Some(PublishDiagnosticsParams {
-            uri: document_url.clone(),
-            diagnostics: diags.clone(),
-            version: None,
+            uri: document_url,
+            diagnostics: diags,
+            version,
         }))
         .await
         .unwrap_or_else(|err| tracing::error!("{err}"));
-
-    if !diags.is_empty() {
-        return;
-    }
-
-    let workspaces = context.workspaces.read().await;
-    let ws = workspaces.by_document(&document_url);
-
-    let doc = match ws.documents.get(&document_url) {
-        Some(doc) => doc,
-        None => return,
-    };
-
-    collect_hir_errors(&document_url, doc, &ws.hir, &mut diags);
-    drop(workspaces);
-
-    context.clone().env.spawn_local(async move {
-        context
-            .write_notification::<notification::PublishDiagnostics, _>(Some(
-                PublishDiagnosticsParams {
-                    uri: document_url.clone(),
-                    diagnostics: diags.clone(),
-                    version: None,
-                },
-            ))
-            .await
-            .unwrap_or_else(|err| tracing::error!("{err}"));
-    });
 }
 
 #[tracing::instrument(skip_all)]
@@ -97,6 +103,32 @@ pub(crate) async fn clear_diagnostics<E: Environment>(
         .unwrap_or_else(|err| tracing::error!("{}", err));
 }
 
+/// Collects the diagnostics of a document the same way
+/// they are published, hir errors are only included
+/// if there are no syntax errors.
+pub(crate) fn document_diagnostics(uri: &Url, doc: &Document, hir: &Hir) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    collect_syntax_errors(doc, &mut diags);
+
+    if diags.is_empty() {
+        collect_hir_errors(uri, doc, hir, &mut diags);
+    }
+
+    diags
+}
+
+/// An identifier for the diagnostics of a document, so that
+/// clients can be told when their diagnostics are unchanged.
+///
+/// The diagnostics can only change if the document changes,
+/// or if the workspace is resolved again.
+pub(crate) fn diagnostics_result_id(version: Option<i32>, generation: u64) -> String {
+    match version {
+        Some(version) => format!("{version}-{generation}"),
+        None => format!("disk-{generation}"),
+    }
+}
+
 #[tracing::instrument(skip_all)]
 fn collect_syntax_errors(doc: &Document, diags: &mut Vec<Diagnostic>) {
     diags.extend(doc.parse.errors.iter().map(|e| {
@@ -166,7 +198,7 @@ fn collect_hir_errors(uri: &Url, doc: &Document, hir: &Hir, diags: &mut Vec<Diag
                     tags: None,
                     data: None,
                 }),
-                ErrorKind::UnresolvedImport { import } => diags.push(Diagnostic {
+                ErrorKind::UnresolvedImport { import, .. } => diags.push(Diagnostic {
                     range: doc
                         .mapper
                         .range(hir[*import].source.text_range.unwrap_or_default())
@@ -13,21 +13,39 @@ pub(crate) use workspaces::*;
 mod folding_ranges;
 pub(crate) use folding_ranges::*;
 
+mod selection_range;
+pub(crate) use selection_range::*;
+
 mod goto;
 pub(crate) use goto::*;
 
 mod references;
 pub(crate) use references::*;
 
+mod document_highlight;
+pub(crate) use document_highlight::*;
+
 mod document_symbols;
 pub(crate) use document_symbols::*;
 
+mod document_link;
+pub(crate) use document_link::*;
+
+mod code_lens;
+pub(crate) use code_lens::*;
+
+mod execute_command;
+pub(crate) use execute_command::*;
+
 mod syntax_tree;
 pub(crate) use syntax_tree::*;
 
 mod convert_offsets;
 pub(crate) use convert_offsets::*;
 
+mod server_status;
+pub(crate) use server_status::*;
+
 mod hover;
 pub(crate) use hover::*;
 
@@ -37,8 +55,14 @@ pub(crate) use rename::*;
 mod watch;
 pub(crate) use watch::*;
 
+mod file_operations;
+pub(crate) use file_operations::*;
+
 mod completion;
 pub(crate) use completion::*;
 
 mod semantic_tokens;
 pub(crate) use semantic_tokens::*;
+
+mod diagnostics;
+pub(crate) use diagnostics::*;
@@ -0,0 +1,151 @@
+use super::{collect_references, CHECK_COMMAND};
+use crate::{utils::check_request, world::World};
+use lsp_async_stub::{rpc, util::LspExt, Context, Params};
+use lsp_types::{CodeLens, CodeLensParams, Command, Position, Range};
+use rhai_common::{environment::Environment, util::Normalize};
+use rhai_hir::{symbol::SymbolKind, Symbol};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::RUN_COMMAND;
+
+/// Data attached to reference count lenses,
+/// the count is computed in [`code_lens_resolve`].
+#[derive(Debug, Serialize, Deserialize)]
+struct CodeLensData {
+    document_url: Url,
+    symbol: Symbol,
+}
+
+pub(crate) async fn code_lens<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<CodeLensParams>,
+) -> Result<Option<Vec<CodeLens>>, rpc::Error> {
+    let p = params.required()?;
+
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&p.text_document.uri);
+
+    let doc = ws.document(&p.text_document.uri)?;
+
+    let document_url = p.text_document.uri.clone().normalize();
+
+    let source = match ws.hir.source_of(&document_url) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let mut lenses = Vec::new();
+
+    if !doc.is_def {
+        let top = Range::new(Position::new(0, 0), Position::new(0, 0));
+        let arguments = Some(vec![Value::String(p.text_document.uri.to_string())]);
+
+        // Scripts cannot be run in the browser.
+        #[cfg(not(target_arch = "wasm32"))]
+        lenses.push(CodeLens {
+            range: top,
+            command: Some(Command {
+                title: "▶ Run script".into(),
+                command: RUN_COMMAND.into(),
+                arguments: arguments.clone(),
+            }),
+            data: None,
+        });
+
+        lenses.push(CodeLens {
+            range: top,
+            command: Some(Command {
+                title: "Check".into(),
+                command: CHECK_COMMAND.into(),
+                arguments,
+            }),
+            data: None,
+        });
+    }
+
+    let module_scope = ws.hir[ws.hir[source].module].scope;
+
+    lenses.extend(
+        ws.hir
+            .source_symbols(source)
+            .filter(|(_, data)| data.parent_scope == module_scope)
+            .filter(|(_, data)| match &data.kind {
+                SymbolKind::Fn(_) => true,
+                SymbolKind::Decl(d) => d.is_const && data.export,
+                _ => false,
+            })
+            .filter_map(|(symbol, data)| {
+                let range = doc.mapper.range(data.selection_range()?)?;
+
+                Some(CodeLens {
+                    range: range.into_lsp(),
+                    command: None,
+                    data: serde_json::to_value(CodeLensData {
+                        document_url: document_url.clone(),
+                        symbol,
+                    })
+                    .ok(),
+                })
+            }),
+    );
+
+    Ok(Some(lenses))
+}
+
+pub(crate) async fn code_lens_resolve<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<CodeLens>,
+) -> Result<CodeLens, rpc::Error> {
+    let mut lens = params.required()?;
+
+    let data = match lens
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<CodeLensData>(data).ok())
+    {
+        Some(data) => data,
+        None => return Ok(lens),
+    };
+
+    check_request(&mut context, &data.document_url)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&data.document_url);
+
+    // The symbol might not exist anymore after changes.
+    if ws.hir.symbol(data.symbol).is_none() {
+        lens.command = Some(Command {
+            title: "references unavailable".into(),
+            command: String::new(),
+            arguments: None,
+        });
+        return Ok(lens);
+    }
+
+    let mut locations = Vec::new();
+    collect_references(ws, data.symbol, false, None, &mut locations);
+
+    let title = match locations.len() {
+        1 => "1 reference".to_string(),
+        count => format!("{count} references"),
+    };
+
+    // The arguments are converted into the expected types
+    // by the client, VS Code cannot handle the JSON values.
+    lens.command = Some(Command {
+        title,
+        command: "editor.action.showReferences".into(),
+        arguments: Some(vec![
+            Value::String(data.document_url.to_string()),
+            serde_json::to_value(lens.range.start).unwrap_or_default(),
+            serde_json::to_value(&locations).unwrap_or_default(),
+        ]),
+    });
+
+    Ok(lens)
+}
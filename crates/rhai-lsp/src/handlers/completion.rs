@@ -1,6 +1,6 @@
 use crate::{
-    utils::{documentation_for, signature_of},
-    world::{Document, Workspace, World},
+    utils::{check_request, documentation_for, import_path_of, signature_of},
+    world::{Document, WorkspaceSnapshot, World},
 };
 use itertools::Itertools;
 use lsp_async_stub::{
@@ -14,15 +14,26 @@ use lsp_types::{
 };
 use rhai_common::{environment::Environment, util::Normalize};
 use rhai_hir::{
-    scope::ScopeParent,
+    module::STATIC_URL_SCHEME,
+    scope::{Scope, ScopeParent},
+    source::Source,
     symbol::{ReferenceTarget, SymbolKind, VirtualSymbol},
     ty::Type,
     Hir, Symbol, TypeKind,
 };
-use rhai_rowan::{query::Query, TextRange};
+use rhai_rowan::{
+    query::Query,
+    syntax::{SyntaxKind, SyntaxNode},
+    util::{is_valid_ident, keyword_kind},
+    TextRange, TextSize,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashSet, ffi::OsStr};
+use url::Url;
 
 pub(crate) async fn completion<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<CompletionParams>,
 ) -> Result<Option<CompletionResponse>, rpc::Error> {
     let p = params.required()?;
@@ -30,7 +41,9 @@ pub(crate) async fn completion<E: Environment>(
     let uri = p.text_document_position.text_document.uri;
     let pos = p.text_document_position.position;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&uri);
 
     let doc = ws.document(&uri)?;
@@ -42,37 +55,50 @@ pub(crate) async fn completion<E: Environment>(
         None => return Ok(None),
     };
 
-    let source = match ws.hir.source_of(&uri.clone().normalize()) {
+    let document_url = uri.clone().normalize();
+
+    let source = match ws.hir.source_of(&document_url) {
         Some(s) => s,
         None => return Ok(None),
     };
 
     let query = Query::at(&syntax, offset);
 
+    if let Some(path_range) = query.import_path_range() {
+        let typed = syntax.text().slice(path_range).to_string();
+        return Ok(Some(CompletionResponse::Array(import_path_completion(
+            ws,
+            doc,
+            &document_url,
+            path_range,
+            &typed,
+        ))));
+    }
+
     if query.is_in_comment() {
         return Ok(None);
     }
 
     if query.is_field_access() {
-        if let Some(sym) = ws.hir.symbol_at(source, offset, true) {
+        let binary = ws.hir.symbol_at(source, offset, true).and_then(|sym| {
             let sym_data = &ws.hir[sym];
             match &sym_data.kind {
-                SymbolKind::Binary(b) => Ok(binary_field_access_completion(b, ws, doc, &query)),
-                _ => {
-                    if let Some(b) = ws.hir[sym_data.parent_scope]
-                        .parent
-                        .as_ref()
-                        .and_then(ScopeParent::as_symbol)
-                        .and_then(|&sym| ws.hir[sym].kind.as_binary())
-                    {
-                        Ok(binary_field_access_completion(b, ws, doc, &query))
-                    } else {
-                        Ok(None)
-                    }
-                }
+                SymbolKind::Binary(b) => Some(b),
+                _ => ws.hir[sym_data.parent_scope]
+                    .parent
+                    .as_ref()
+                    .and_then(ScopeParent::as_symbol)
+                    .and_then(|&sym| ws.hir[sym].kind.as_binary()),
             }
-        } else {
-            Ok(None)
+        });
+
+        match binary {
+            Some(b) => {
+                let mut items = binary_field_access_completion(b, ws, doc, &query);
+                items.extend(postfix_completion(b, ws, doc, &syntax, &query));
+                Ok(Some(CompletionResponse::Array(items)))
+            }
+            None => Ok(None),
         }
     } else if query.is_path() {
         let modules = ws
@@ -101,7 +127,7 @@ pub(crate) async fn completion<E: Environment>(
         if idx == 0 {
             return Ok(Some(CompletionResponse::Array(
                 modules
-                    .filter_map(|symbol| reference_completion(&ws.hir, true, symbol))
+                    .filter_map(|symbol| reference_completion(&ws.hir, &document_url, true, symbol))
                     .unique_by(|(symbol, _)| ws.hir.unique_symbol_name(symbol))
                     .map(|(_, c)| c)
                     .collect(),
@@ -141,28 +167,61 @@ pub(crate) async fn completion<E: Environment>(
         Ok(Some(CompletionResponse::Array(
             symbols
                 .into_iter()
-                .filter_map(|symbol| reference_completion(&ws.hir, false, symbol))
+                .filter_map(|symbol| reference_completion(&ws.hir, &document_url, false, symbol))
                 .unique_by(|(symbol, _)| ws.hir.unique_symbol_name(symbol))
                 .map(|(_, c)| c)
                 .collect(),
         )))
     } else if query.can_complete_ref() {
-        Ok(Some(CompletionResponse::Array(
-            ws.hir
-                .visible_symbols_from_offset(source, offset, false)
-                .filter_map(|symbol| {
-                    // Unwrap aliases from import symbols
-                    ws.hir[symbol]
-                        .kind
-                        .as_import()
-                        .and_then(|d| d.alias)
-                        .or(Some(symbol))
-                })
-                .filter_map(|symbol| reference_completion(&ws.hir, false, symbol))
-                .unique_by(|(symbol, _)| ws.hir.unique_symbol_name(symbol))
-                .map(|(_, c)| c)
-                .collect(),
-        )))
+        let scope = ws.hir.scope_at(source, offset, false);
+        let expected_ty = expected_type(&ws.hir, source, offset);
+
+        let mut items: Vec<_> = ws
+            .hir
+            .visible_symbols_from_offset(source, offset, false)
+            .filter_map(|symbol| {
+                // Unwrap aliases from import symbols
+                ws.hir[symbol]
+                    .kind
+                    .as_import()
+                    .and_then(|d| d.alias)
+                    .or(Some(symbol))
+            })
+            .filter_map(|symbol| reference_completion(&ws.hir, &document_url, false, symbol))
+            .unique_by(|(symbol, _)| ws.hir.unique_symbol_name(symbol))
+            .map(|(symbol, c)| CompletionItem {
+                sort_text: Some(reference_sort_text(
+                    &ws.hir,
+                    scope,
+                    expected_ty,
+                    symbol,
+                    &c.label,
+                )),
+                ..c
+            })
+            .collect();
+
+        let is_top_level = scope == Some(ws.hir[ws.hir[source].module].scope);
+
+        items.extend(keyword_completion(
+            &query,
+            doc.is_def,
+            is_top_level,
+            &syntax,
+        ));
+
+        if !doc.is_def {
+            items.extend(auto_import_completion(
+                ws,
+                doc,
+                &syntax,
+                source,
+                offset,
+                &document_url,
+            ));
+        }
+
+        Ok(Some(CompletionResponse::Array(items)))
     } else if query.can_complete_op() {
         Ok(Some(CompletionResponse::Array(
             ws.hir
@@ -199,41 +258,639 @@ pub(crate) async fn completion<E: Environment>(
 
 fn binary_field_access_completion<E: Environment>(
     b: &rhai_hir::symbol::BinarySymbol,
-    ws: &Workspace<E>,
+    ws: &WorkspaceSnapshot<E>,
     doc: &Document,
     query: &Query,
-) -> std::option::Option<lsp_types::CompletionResponse> {
+) -> Vec<CompletionItem> {
     if let Some(lhs_ty) = b.lhs.map(|lhs| ws.hir[lhs].ty) {
         let lhs_ty_data = &ws.hir[lhs_ty];
 
         match &lhs_ty_data.kind {
-            TypeKind::Object(o) => Some(CompletionResponse::Array(
-                o.fields
-                    .iter()
-                    .map(|(name, ty)| {
-                        field_completion(
-                            doc,
-                            &ws.hir,
-                            name,
-                            *ty,
-                            query.ident().map(|t| t.text_range()),
-                        )
-                    })
-                    .collect(),
-            )),
+            TypeKind::Object(o) => o
+                .fields
+                .iter()
+                .map(|(name, ty)| {
+                    field_completion(
+                        doc,
+                        &ws.hir,
+                        name,
+                        *ty,
+                        query.ident().map(|t| t.text_range()),
+                    )
+                })
+                .collect(),
             _ => {
                 // TODO: handle the rest of the types,
                 // functions with getters and known `this` type.
-                None
+                Vec::new()
             }
         }
     } else {
-        None
+        Vec::new()
+    }
+}
+
+/// Postfix templates as `(label, detail, snippet)`,
+/// `{expr}` is replaced with the receiver expression.
+const POSTFIX_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("if", "if expr {}", "if {expr} {\n\t$0\n}"),
+    ("not", "!expr", "!{expr}"),
+    (
+        "for",
+        "for item in expr {}",
+        "for ${1:item} in {expr} {\n\t$0\n}",
+    ),
+    ("let", "let name = expr;", "let ${1:name} = {expr};$0"),
+    ("print", "print(expr);", "print({expr});$0"),
+    ("debug", "debug(expr);", "debug({expr});$0"),
+    (
+        "switch",
+        "switch expr {}",
+        "switch {expr} {\n\t${1:_} => $0\n}",
+    ),
+    ("return", "return expr;", "return {expr};$0"),
+    ("type_of", "type_of(expr)", "type_of({expr})$0"),
+];
+
+/// Completes postfix templates that wrap the receiver
+/// of a field access, e.g. `foo.if`.
+fn postfix_completion<E: Environment>(
+    b: &rhai_hir::symbol::BinarySymbol,
+    ws: &WorkspaceSnapshot<E>,
+    doc: &Document,
+    syntax: &SyntaxNode,
+    query: &Query,
+) -> Vec<CompletionItem> {
+    let lhs = match b.lhs {
+        Some(lhs) => lhs,
+        None => return Vec::new(),
+    };
+
+    let lhs_range = match query.field_access_receiver_range() {
+        Some(range) => range,
+        None => return Vec::new(),
+    };
+
+    let end = query
+        .ident()
+        .map_or(query.offset, |ident| ident.text_range().end())
+        .max(query.offset);
+
+    let edit_range = match doc.mapper.range(TextRange::new(lhs_range.start(), end)) {
+        Some(range) => range.into_lsp(),
+        None => return Vec::new(),
+    };
+
+    let lhs_text = syntax.text().slice(lhs_range).to_string();
+
+    let escaped_lhs = lhs_text
+        .replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}");
+
+    let lhs_is_range = ws.hir[lhs]
+        .kind
+        .as_binary()
+        .is_some_and(|b| b.lookup_text == ".." || b.lookup_text == "..=");
+
+    let lhs_ty = &ws.hir[ws.hir.follow_aliases(ws.hir[lhs].ty)].kind;
+
+    POSTFIX_TEMPLATES
+        .iter()
+        .filter(|(label, ..)| match *label {
+            "if" => matches!(lhs_ty, TypeKind::Bool | TypeKind::Unknown),
+            "for" => {
+                lhs_is_range
+                    || matches!(
+                        lhs_ty,
+                        TypeKind::Array(_) | TypeKind::Iterator(_) | TypeKind::Unknown
+                    )
+            }
+            _ => true,
+        })
+        .map(|(label, detail, snippet)| CompletionItem {
+            label: (*label).to_string(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            detail: Some((*detail).to_string()),
+            filter_text: Some(format!("{lhs_text}.{label}")),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range: edit_range,
+                new_text: snippet.replace("{expr}", &escaped_lhs),
+            })),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+/// Completes the path in an import string with files and directories
+/// relative to the current module, and with the URLs of modules
+/// that are already known.
+///
+/// An empty path is completed with the modules next to the current one.
+fn import_path_completion<E: Environment>(
+    ws: &WorkspaceSnapshot<E>,
+    doc: &Document,
+    document_url: &Url,
+    path_range: TextRange,
+    typed: &str,
+) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    let mut listed_paths = HashSet::new();
+
+    // Only the last segment of the path is replaced for files.
+    let typed_dir = typed.rfind('/').map_or("", |idx| &typed[..=idx]);
+    let segment_range = TextRange::new(
+        path_range.start() + TextSize::of(typed_dir),
+        path_range.end(),
+    );
+
+    let dir = if typed.is_empty() { "./" } else { typed_dir };
+
+    // The part of the directory that is not typed yet.
+    let dir_prefix = &dir[typed_dir.len()..];
+
+    let relative = dir.starts_with('.');
+
+    if relative && !dir.is_empty() {
+        let dir_path = document_url
+            .join(dir)
+            .ok()
+            .and_then(|url| ws.env.url_to_file_path(&url));
+
+        let document_path = ws
+            .env
+            .url_to_file_path(document_url)
+            .map(Normalize::normalize);
+
+        let entries = match dir_path {
+            Some(dir_path) => ws
+                .env
+                .glob_files(&dir_path.join("*").to_string_lossy())
+                .map_or_else(
+                    |error| {
+                        tracing::debug!(%error, "failed to list directory");
+                        Vec::new()
+                    },
+                    Normalize::normalize,
+                ),
+            None => Vec::new(),
+        };
+
+        for path in entries {
+            if Some(&path) == document_path.as_ref() {
+                continue;
+            }
+
+            let file_name = match path.file_name().and_then(OsStr::to_str) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let (label, kind) = if ws.env.is_dir(&path) {
+                (format!("{file_name}/"), CompletionItemKind::FOLDER)
+            } else {
+                if let Some(rule) = &ws.rhai_config.source.file_rule {
+                    if !rule.is_match(&path) {
+                        continue;
+                    }
+                }
+
+                // Definitions describe the script module next to them.
+                let label = match file_name.strip_suffix(".d.rhai") {
+                    Some(name) => format!("{name}.rhai"),
+                    None => file_name.to_string(),
+                };
+
+                (label, CompletionItemKind::FILE)
+            };
+
+            if !listed_paths.insert(format!("{dir}{label}")) {
+                continue;
+            }
+
+            let new_text = format!("{dir_prefix}{label}");
+
+            items.push(CompletionItem {
+                label,
+                kind: Some(kind),
+                command: (kind == CompletionItemKind::FOLDER).then(trigger_completion),
+                filter_text: Some(new_text.clone()),
+                text_edit: doc.mapper.range(segment_range).map(|range| {
+                    CompletionTextEdit::Edit(TextEdit {
+                        new_text,
+                        range: range.into_lsp(),
+                    })
+                }),
+                ..CompletionItem::default()
+            });
+        }
     }
+
+    for (_, module) in ws.hir.modules() {
+        let module_url = match module.url() {
+            Some(url) if url != document_url && url.scheme() != STATIC_URL_SCHEME => url,
+            _ => continue,
+        };
+
+        if !typed.is_empty() && relative != (module_url.scheme() == "file") {
+            continue;
+        }
+
+        let import_path = match import_path_of(document_url, module_url) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        if listed_paths.contains(&import_path) {
+            continue;
+        }
+
+        let docs = module.docs.trim();
+
+        items.push(CompletionItem {
+            label: import_path.clone(),
+            kind: Some(CompletionItemKind::MODULE),
+            documentation: (!docs.is_empty()).then(|| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: docs.to_string(),
+                })
+            }),
+            text_edit: doc.mapper.range(path_range).map(|range| {
+                CompletionTextEdit::Edit(TextEdit {
+                    new_text: import_path,
+                    range: range.into_lsp(),
+                })
+            }),
+            ..CompletionItem::default()
+        });
+    }
+
+    items
 }
 
+/// Keywords completed at the start of statements as `(keyword, snippet)`.
+const STMT_KEYWORDS: &[(&str, &str)] = &[
+    ("let", "let ${1:name} = $0;"),
+    ("const", "const ${1:NAME} = $0;"),
+    ("if", "if ${1:condition} {\n\t$0\n}"),
+    ("switch", "switch ${1:value} {\n\t${2:_} => $0\n}"),
+    ("for", "for ${1:item} in ${2:iterable} {\n\t$0\n}"),
+    ("while", "while ${1:condition} {\n\t$0\n}"),
+    ("loop", "loop {\n\t$0\n}"),
+    ("do", "do {\n\t$0\n} while ${1:condition};"),
+    ("try", "try {\n\t$1\n} catch (${2:err}) {\n\t$0\n}"),
+    ("return", "return $0;"),
+    ("throw", "throw $0;"),
+];
+
+/// Keywords completed at the start of statements
+/// only in the top-level scope of scripts.
+const TOP_LEVEL_KEYWORDS: &[(&str, &str)] = &[
+    ("fn", "fn ${1:name}($2) {\n\t$0\n}"),
+    ("import", "import \"$1\" as ${2:name};"),
+    ("export", "export ${1:name};"),
+];
+
+/// Keywords completed inside expressions.
+const EXPR_KEYWORDS: &[(&str, &str)] = &[
+    ("if", "if ${1:condition} { $2 } else { $0 }"),
+    ("switch", "switch ${1:value} {\n\t${2:_} => $0\n}"),
+];
+
+/// Keywords completed in the body of loops.
+const LOOP_KEYWORDS: &[(&str, &str)] = &[("break", "break;"), ("continue", "continue;")];
+
+/// Keywords completed after an `if` without an `else` branch.
+const ELSE_KEYWORDS: &[(&str, &str)] = &[
+    ("else", "else {\n\t$0\n}"),
+    ("else if", "else if ${1:condition} {\n\t$0\n}"),
+];
+
+/// Keywords completed at the top level of definition files.
+const DEF_KEYWORDS: &[(&str, &str)] = &[
+    ("fn", "fn ${1:name}($2)$0;"),
+    ("op", "op ${1:op}(${2:?}, ${3:?}) -> ${4:?};"),
+    ("type", "type ${1:Name} = ${0:?};"),
+];
+
+fn keyword_completion(
+    query: &Query,
+    is_def: bool,
+    is_top_level: bool,
+    syntax: &SyntaxNode,
+) -> Vec<CompletionItem> {
+    let mut keywords = Vec::new();
+
+    if is_def {
+        if query.is_stmt_start() {
+            if !syntax
+                .children()
+                .any(|n| n.kind() == SyntaxKind::DEF_MODULE_DECL)
+            {
+                keywords.push(("module", "module ${1:name};"));
+            }
+
+            keywords.extend(DEF_KEYWORDS);
+        }
+    } else if query.is_stmt_start() {
+        keywords.extend(STMT_KEYWORDS);
+
+        if is_top_level {
+            keywords.extend(TOP_LEVEL_KEYWORDS);
+        }
+
+        if query.is_in_loop() {
+            keywords.extend(LOOP_KEYWORDS);
+        }
+
+        if query.is_after_if() {
+            keywords.extend(ELSE_KEYWORDS);
+        }
+    } else {
+        keywords.extend(EXPR_KEYWORDS);
+    }
+
+    keywords
+        .into_iter()
+        .map(|(keyword, snippet)| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            sort_text: Some(format!("2{keyword}")),
+            insert_text: Some(snippet.to_string()),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+/// Completes exported symbols of modules that are not yet imported,
+/// adding the import statement on acceptance.
+fn auto_import_completion<E: Environment>(
+    ws: &WorkspaceSnapshot<E>,
+    doc: &Document,
+    syntax: &SyntaxNode,
+    source: Source,
+    offset: TextSize,
+    document_url: &Url,
+) -> Vec<CompletionItem> {
+    let current_module = ws.hir.module_by_source(source);
+
+    let imported_modules = ws
+        .hir
+        .source_symbols(source)
+        .filter_map(|(_, data)| data.kind.as_import()?.target)
+        .collect::<HashSet<_>>();
+
+    let mut visible_names = ws
+        .hir
+        .visible_symbols_from_offset(source, offset, false)
+        .filter_map(|symbol| ws.hir[symbol].name(&ws.hir).map(ToString::to_string))
+        .collect::<HashSet<_>>();
+
+    let import_range = match doc
+        .mapper
+        .range(TextRange::empty(import_insert_offset(syntax)))
+    {
+        Some(range) => range.into_lsp(),
+        None => return Vec::new(),
+    };
+
+    let mut items = Vec::new();
+
+    for (module, module_data) in ws.hir.modules() {
+        if Some(module) == current_module || imported_modules.contains(&module) {
+            continue;
+        }
+
+        let module_url = match module_data.url() {
+            Some(url) if url.scheme() != STATIC_URL_SCHEME => url,
+            _ => continue,
+        };
+
+        let exported = ws
+            .hir
+            .scope_symbols(module_data.scope)
+            .filter(|&symbol| ws.hir[symbol].export)
+            .filter_map(|symbol| reference_completion(&ws.hir, document_url, false, symbol))
+            .collect::<Vec<_>>();
+
+        if exported.is_empty() {
+            continue;
+        }
+
+        let import_path = match import_path_of(document_url, module_url) {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let alias = match module_alias(module_url, &visible_names) {
+            Some(alias) => alias,
+            None => continue,
+        };
+
+        visible_names.insert(alias.clone());
+
+        let import_text = format!("import \"{import_path}\" as {alias};");
+
+        let import_edit = TextEdit {
+            range: import_range,
+            new_text: if import_range.start == lsp_types::Position::default() {
+                format!("{import_text}\n")
+            } else {
+                format!("\n{import_text}")
+            },
+        };
+
+        items.extend(exported.into_iter().map(|(symbol, item)| {
+            CompletionItem {
+                insert_text: item.insert_text.map(|text| format!("{alias}::{text}")),
+                additional_text_edits: Some(vec![import_edit.clone()]),
+                sort_text: Some(format!("3{}", item.label)),
+                data: Some(
+                    CompletionData {
+                        import_path: Some(import_path.clone()),
+                        ..CompletionData::new(document_url, symbol)
+                    }
+                    .into_value(),
+                ),
+                ..item
+            }
+        }));
+    }
+
+    items
+}
+
+/// The offset where new imports are added, after the shebang,
+/// the module docs and the existing imports at the top of the file.
+fn import_insert_offset(syntax: &SyntaxNode) -> TextSize {
+    let mut offset = TextSize::from(0);
+
+    for element in syntax.children_with_tokens() {
+        match (element.as_node(), element.as_token()) {
+            (_, Some(token)) => match token.kind() {
+                SyntaxKind::WHITESPACE => {}
+                SyntaxKind::SHEBANG => offset = token.text_range().end(),
+                SyntaxKind::COMMENT_LINE if token.text().starts_with("//!") => {
+                    offset = token.text_range().end();
+                }
+                SyntaxKind::COMMENT_BLOCK if token.text().starts_with("/*!") => {
+                    offset = token.text_range().end();
+                }
+                _ => break,
+            },
+            (Some(node), _) => {
+                let is_import = node
+                    .descendants()
+                    .find(|n| {
+                        !matches!(
+                            n.kind(),
+                            SyntaxKind::STMT
+                                | SyntaxKind::ITEM
+                                | SyntaxKind::DOC
+                                | SyntaxKind::EXPR
+                        )
+                    })
+                    .is_some_and(|n| n.kind() == SyntaxKind::EXPR_IMPORT);
+
+                if !is_import {
+                    break;
+                }
+
+                offset = node.text_range().end();
+            }
+            (None, None) => break,
+        }
+    }
+
+    offset
+}
+
+/// An identifier for importing the module with the given URL
+/// based on its file name that does not clash with the given names.
+fn module_alias(module_url: &Url, taken: &HashSet<String>) -> Option<String> {
+    let file_name = module_url.path_segments()?.next_back()?;
+    let name = file_name.split('.').next()?;
+
+    let mut alias = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    if alias.is_empty() {
+        return None;
+    }
+
+    // Keywords, reserved ones included, e.g. `loop_` for `loop.rhai`.
+    if keyword_kind(&alias).is_some() {
+        alias.push('_');
+    }
+
+    // Names that are still not identifiers, e.g. ones starting with a digit.
+    if !is_valid_ident(&alias) {
+        alias.insert_str(0, "m_");
+    }
+
+    let mut unique_alias = alias.clone();
+    let mut idx = 1;
+
+    while taken.contains(&unique_alias) {
+        unique_alias = format!("{alias}_{idx}");
+        idx += 1;
+    }
+
+    Some(unique_alias)
+}
+
+/// Ranks symbols matching the expected type first,
+/// then by the distance of their scope from the cursor.
+fn reference_sort_text(
+    hir: &Hir,
+    scope: Option<Scope>,
+    expected_ty: Option<Type>,
+    symbol: Symbol,
+    label: &str,
+) -> String {
+    let ty = match &hir[hir[symbol].ty].kind {
+        TypeKind::Fn(f) => f.ret,
+        _ => hir[symbol].ty,
+    };
+
+    let type_rank = match expected_ty {
+        Some(expected) if is_same_type(hir, expected, ty) => 0,
+        _ => 1,
+    };
+
+    let distance = scope
+        .and_then(|scope| scope_distance(hir, scope, hir[symbol].parent_scope))
+        .unwrap_or(9999)
+        .min(9999);
+
+    format!("{type_rank}{distance:04}{label}")
+}
+
+/// The number of scopes between the given scope and one of its ancestors.
+fn scope_distance(hir: &Hir, from: Scope, to: Scope) -> Option<usize> {
+    let mut scope = from;
+    let mut distance = 0;
+
+    while scope != to {
+        scope = match hir[scope].parent? {
+            ScopeParent::Scope(parent) => parent,
+            ScopeParent::Symbol(symbol) => hir[symbol].parent_scope,
+        };
+        distance += 1;
+    }
+
+    Some(distance)
+}
+
+fn is_same_type(hir: &Hir, a: Type, b: Type) -> bool {
+    if matches!(hir[a].kind, TypeKind::Unknown) || matches!(hir[b].kind, TypeKind::Unknown) {
+        return false;
+    }
+
+    a.is(hir, b, true)
+}
+
+/// The type expected at the reference being typed
+/// if it is a function argument or the right side of a binary expression.
+fn expected_type(hir: &Hir, source: Source, offset: TextSize) -> Option<Type> {
+    let symbol = hir.symbol_at(source, offset, true)?;
+    hir[symbol].kind.as_reference()?;
+
+    let parent_scope = hir[symbol].parent_scope;
+
+    if let Some(binary) = hir[parent_scope]
+        .parent
+        .as_ref()
+        .and_then(ScopeParent::as_symbol)
+        .and_then(|&parent| hir[parent].kind.as_binary())
+    {
+        if binary.rhs == Some(symbol) && !binary.is_field_access() {
+            return binary.lhs.map(|lhs| hir[lhs].ty);
+        }
+        return None;
+    }
+
+    hir.scope_symbols(parent_scope).find_map(|call_symbol| {
+        let call = hir[call_symbol].kind.as_call()?;
+        let idx = call.arguments.iter().position(|&arg| arg == symbol)?;
+
+        match &hir[hir[call.lhs?].ty].kind {
+            TypeKind::Fn(f) => f.params.get(idx).map(|(_, ty)| *ty),
+            _ => None,
+        }
+    })
+}
+
+/// Completes a reference to the symbol, the signature and docs
+/// are added later in [`completion_resolve`].
 fn reference_completion(
     hir: &Hir,
+    document_url: &Url,
     ident_only: bool,
     symbol: Symbol,
 ) -> Option<(Symbol, CompletionItem)> {
@@ -242,11 +899,7 @@ fn reference_completion(
             symbol,
             CompletionItem {
                 label: f.name.clone(),
-                detail: Some(signature_of(hir, symbol)),
-                documentation: Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: documentation_for(hir, symbol, false),
-                })),
+                data: Some(CompletionData::new(document_url, symbol).into_value()),
                 kind: Some(CompletionItemKind::FUNCTION),
                 insert_text: Some(format!("{}($0)", &f.name)),
                 insert_text_format: Some(InsertTextFormat::SNIPPET),
@@ -257,11 +910,7 @@ fn reference_completion(
             symbol,
             CompletionItem {
                 label: d.name.clone(),
-                detail: Some(signature_of(hir, symbol)),
-                documentation: Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: documentation_for(hir, symbol, false),
-                })),
+                data: Some(CompletionData::new(document_url, symbol).into_value()),
                 kind: Some(if d.is_const {
                     CompletionItemKind::CONSTANT
                 } else if d.is_import {
@@ -286,11 +935,7 @@ fn reference_completion(
             symbol,
             CompletionItem {
                 label: m.name.clone(),
-                detail: Some(signature_of(hir, symbol)),
-                documentation: Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: documentation_for(hir, symbol, false),
-                })),
+                data: Some(CompletionData::new(document_url, symbol).into_value()),
                 kind: Some(CompletionItemKind::MODULE),
                 insert_text: if ident_only || hir[hir[m.module].scope].is_empty() {
                     Some(m.name.clone())
@@ -333,6 +978,69 @@ fn field_completion(
     }
 }
 
+/// Data attached to completion items
+/// for resolving their details and docs lazily.
+#[derive(Debug, Serialize, Deserialize)]
+struct CompletionData {
+    document_url: Url,
+    symbol: Symbol,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    import_path: Option<String>,
+}
+
+impl CompletionData {
+    fn new(document_url: &Url, symbol: Symbol) -> Self {
+        Self {
+            document_url: document_url.clone(),
+            symbol,
+            import_path: None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+pub(crate) async fn completion_resolve<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<CompletionItem>,
+) -> Result<CompletionItem, rpc::Error> {
+    let mut item = params.required()?;
+
+    let data = match item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<CompletionData>(data).ok())
+    {
+        Some(data) => data,
+        None => return Ok(item),
+    };
+
+    check_request(&mut context, &data.document_url)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&data.document_url);
+
+    // The symbol might not exist anymore after changes.
+    if ws.hir.symbol(data.symbol).is_none() {
+        return Ok(item);
+    }
+
+    let signature = signature_of(&ws.hir, data.symbol);
+
+    item.detail = Some(match &data.import_path {
+        Some(import_path) => format!("{signature} (from {import_path:?})"),
+        None => signature,
+    });
+    item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: documentation_for(&ws.hir, data.symbol, false),
+    }));
+
+    Ok(item)
+}
+
 fn trigger_completion() -> Command {
     Command {
         command: "editor.action.triggerSuggest".into(),
@@ -11,7 +11,7 @@ pub(crate) async fn convert_offsets<E: Environment>(
     params: Params<ConvertOffsetsParams>,
 ) -> Result<Option<ConvertOffsetsResult>, rpc::Error> {
     let p = params.required()?;
-    let workspaces = context.workspaces.read().await;
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&p.uri);
 
     let doc = ws.document(&p.uri)?;
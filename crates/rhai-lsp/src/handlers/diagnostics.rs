@@ -0,0 +1,163 @@
+use crate::{
+    diagnostics::{clear_diagnostics, diagnostics_result_id, document_diagnostics},
+    lsp_ext::request::{
+        DocumentDiagnosticParams, DocumentDiagnosticReport, FullDocumentDiagnosticReport,
+        UnchangedDocumentDiagnosticReport, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+        WorkspaceDocumentDiagnosticReport,
+    },
+    utils::{check_request, RequestCheckpoint},
+    world::World,
+};
+use futures::future::{select, Either};
+use lsp_async_stub::{rpc, Context, Params};
+use lsp_types::{Diagnostic, Url};
+use rhai_common::environment::Environment;
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn document_diagnostic<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<DocumentDiagnosticParams>,
+) -> Result<DocumentDiagnosticReport, rpc::Error> {
+    let p = params.required()?;
+    let document_url = p.text_document.uri;
+
+    check_request(&mut context, &document_url)?;
+    start_pulling(context.clone()).await;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&document_url);
+    let doc = ws.document(&document_url)?;
+
+    Ok(diagnostic_report(
+        diagnostics_result_id(doc.version, ws.generation),
+        p.previous_result_id.as_deref(),
+        || document_diagnostics(&document_url, doc, &ws.hir),
+    ))
+}
+
+/// Reports the diagnostics of every document in the workspaces.
+///
+/// If none of them changed since the previous results,
+/// the request is kept open until they do.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn workspace_diagnostic<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<WorkspaceDiagnosticParams>,
+) -> Result<WorkspaceDiagnosticReport, rpc::Error> {
+    let p = params.required()?;
+
+    start_pulling(context.clone()).await;
+
+    let previous_result_ids = p
+        .previous_result_ids
+        .into_iter()
+        .map(|id| (id.uri, id.value))
+        .collect::<HashMap<_, _>>();
+
+    loop {
+        let world = context.clone();
+        let changed = world.diagnostics_changed.notified();
+
+        let items = workspace_reports(&mut context, &previous_result_ids).await?;
+
+        if items
+            .iter()
+            .any(|item| matches!(item.report, DocumentDiagnosticReport::Full(_)))
+        {
+            return Ok(WorkspaceDiagnosticReport { items });
+        }
+
+        let cancelled = matches!(
+            select(Box::pin(changed), context.cancel_token()).await,
+            Either::Right(_)
+        );
+
+        if cancelled {
+            return Err(rpc::Error::request_cancelled());
+        }
+    }
+}
+
+/// Stops publishing diagnostics once the client pulls them,
+/// and clears the ones that were already published.
+async fn start_pulling<E: Environment>(context: Context<World<E>>) {
+    if context.pull_diagnostics.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tracing::info!("client pulls diagnostics");
+
+    let document_urls = context
+        .workspaces
+        .snapshot()
+        .iter()
+        .flat_map(|(_, ws)| ws.documents.keys().cloned())
+        .collect::<Vec<_>>();
+
+    for document_url in document_urls {
+        clear_diagnostics(context.clone(), document_url).await;
+    }
+}
+
+async fn workspace_reports<E: Environment>(
+    context: &mut Context<World<E>>,
+    previous_result_ids: &HashMap<Url, String>,
+) -> Result<Vec<WorkspaceDocumentDiagnosticReport>, rpc::Error> {
+    let workspaces = context.workspaces.snapshot();
+    let mut checkpoint = RequestCheckpoint::new(context);
+    let mut items = Vec::new();
+
+    for (_, ws) in workspaces.iter() {
+        for (document_url, doc) in &ws.documents {
+            checkpoint.check(context, document_url).await?;
+
+            items.push(WorkspaceDocumentDiagnosticReport {
+                uri: document_url.clone(),
+                version: doc.version,
+                report: diagnostic_report(
+                    diagnostics_result_id(doc.version, ws.generation),
+                    previous_result_ids.get(document_url).map(String::as_str),
+                    || document_diagnostics(document_url, doc, &ws.hir),
+                ),
+            });
+        }
+    }
+
+    // Clear the diagnostics of documents that no longer exist.
+    for (document_url, result_id) in previous_result_ids {
+        if !items.iter().any(|item| &item.uri == document_url) {
+            items.push(WorkspaceDocumentDiagnosticReport {
+                uri: document_url.clone(),
+                version: None,
+                report: diagnostic_report(
+                    String::from(REMOVED_RESULT_ID),
+                    Some(result_id),
+                    Vec::new,
+                ),
+            });
+        }
+    }
+
+    Ok(items)
+}
+
+/// The result id of documents that no longer exist.
+const REMOVED_RESULT_ID: &str = "removed";
+
+/// Reports the diagnostics if they changed since the previous result,
+/// they are only collected in that case.
+fn diagnostic_report(
+    result_id: String,
+    previous_result_id: Option<&str>,
+    diags: impl FnOnce() -> Vec<Diagnostic>,
+) -> DocumentDiagnosticReport {
+    if previous_result_id == Some(result_id.as_str()) {
+        DocumentDiagnosticReport::Unchanged(UnchangedDocumentDiagnosticReport { result_id })
+    } else {
+        DocumentDiagnosticReport::Full(FullDocumentDiagnosticReport {
+            result_id: Some(result_id),
+            items: diags(),
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{utils::check_request, world::World};
+use core::iter;
+use lsp_async_stub::{
+    rpc::Error,
+    util::{LspExt, Position},
+    Context, Params,
+};
+use lsp_types::{DocumentHighlight, DocumentHighlightKind, DocumentHighlightParams};
+use rhai_common::{environment::Environment, util::Normalize};
+use rhai_hir::symbol::{ReferenceTarget, SymbolAccess, SymbolKind};
+
+#[tracing::instrument(skip_all)]
+pub async fn document_highlight<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<DocumentHighlightParams>,
+) -> Result<Option<Vec<DocumentHighlight>>, Error> {
+    let p = params.required()?;
+    let document_uri = p.text_document_position_params.text_document.uri;
+
+    check_request(&mut context, &document_uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&document_uri);
+    let doc = ws.document(&document_uri)?;
+
+    let position = p.text_document_position_params.position;
+    let offset = match doc.mapper.offset(Position::from_lsp(position)) {
+        Some(ofs) => ofs,
+        None => {
+            tracing::error!(?position, "document position not found");
+            return Ok(None);
+        }
+    };
+
+    let source = match ws.hir.source_of(&document_uri.clone().normalize()) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let symbol = match ws.hir.symbol_selection_at(source, offset, true) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let target = match &ws.hir[symbol].kind {
+        SymbolKind::Reference(r) => match r.target {
+            Some(ReferenceTarget::Symbol(target)) => target,
+            _ => return Ok(None),
+        },
+        _ => symbol,
+    };
+
+    let references = match &ws.hir[target].kind {
+        SymbolKind::Fn(f) => &f.references,
+        SymbolKind::Decl(d) => &d.references,
+        _ => return Ok(None),
+    };
+
+    let highlights = iter::once(target)
+        .chain(references.iter().copied())
+        .filter(|&sym| ws.hir[sym].source.is(source))
+        .filter_map(|sym| {
+            let range = ws.hir[sym]
+                .selection_range()
+                .and_then(|range| doc.mapper.range(range))?;
+
+            let kind = match ws.hir.symbol_access(sym) {
+                SymbolAccess::Read => DocumentHighlightKind::READ,
+                SymbolAccess::Write => DocumentHighlightKind::WRITE,
+            };
+
+            Some(DocumentHighlight {
+                range: range.into_lsp(),
+                kind: Some(kind),
+            })
+        })
+        .collect();
+
+    Ok(Some(highlights))
+}
@@ -0,0 +1,74 @@
+use crate::{utils::check_request, world::World};
+use lsp_async_stub::{rpc, util::LspExt, Context, Params};
+use lsp_types::{DocumentLink, DocumentLinkParams};
+use rhai_common::{environment::Environment, util::Normalize};
+use rhai_hir::symbol::SymbolKind;
+use rhai_rowan::{TextRange, TextSize};
+
+pub(crate) async fn document_links<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<DocumentLinkParams>,
+) -> Result<Option<Vec<DocumentLink>>, rpc::Error> {
+    let p = params.required()?;
+
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&p.text_document.uri);
+
+    let doc = ws.document(&p.text_document.uri)?;
+
+    let source = match ws.hir.source_of(&p.text_document.uri.clone().normalize()) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let links = ws
+        .hir
+        .source_symbols(source)
+        .filter_map(|(symbol, data)| match &data.kind {
+            SymbolKind::Import(import) => Some((symbol, import)),
+            _ => None,
+        })
+        .filter_map(|(symbol, import)| {
+            let path_range = ws.hir[import.expr?].text_range()?;
+
+            let from = ws
+                .hir
+                .module_by_symbol(symbol)
+                .and_then(|m| ws.hir[m].url());
+
+            let module_url = ws
+                .hir
+                .resolve_import_url(from, import.import_path(&ws.hir)?)?;
+
+            // Unresolved imports are reported as diagnostics instead.
+            let module = ws.hir.module_by_url(&module_url)?;
+
+            let docs = ws.hir[module].docs.trim();
+
+            // Only link the path without the quotes.
+            let path_range = if path_range.len() > TextSize::from(2) {
+                TextRange::new(
+                    path_range.start() + TextSize::from(1),
+                    path_range.end() - TextSize::from(1),
+                )
+            } else {
+                path_range
+            };
+
+            Some(DocumentLink {
+                range: doc.mapper.range(path_range)?.into_lsp(),
+                target: Some(module_url),
+                tooltip: if docs.is_empty() {
+                    None
+                } else {
+                    Some(docs.to_string())
+                },
+                data: None,
+            })
+        })
+        .collect();
+
+    Ok(Some(links))
+}
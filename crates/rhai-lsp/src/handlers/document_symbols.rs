@@ -1,9 +1,13 @@
 #![allow(deprecated)]
 
-use crate::{utils::signature_of, world::World};
+use crate::{
+    mapper::Mapper,
+    utils::{check_request, signature_of},
+    world::World,
+};
 use lsp_async_stub::{
     rpc,
-    util::{LspExt, Mapper},
+    util::LspExt,
     Context, Params,
 };
 use lsp_types::{DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, SymbolKind};
@@ -15,12 +19,14 @@ use rhai_rowan::{
 };
 
 pub(crate) async fn document_symbols<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<DocumentSymbolParams>,
 ) -> Result<Option<DocumentSymbolResponse>, rpc::Error> {
     let p = params.required()?;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&p.text_document.uri);
 
     let doc = ws.document(&p.text_document.uri)?;
@@ -5,7 +5,7 @@ use lsp_types::{
 };
 use rhai_common::environment::Environment;
 use crate::{
-    diagnostics::{publish_all_diagnostics, publish_diagnostics},
+    diagnostics::{clear_diagnostics, publish_all_diagnostics, publish_diagnostics},
     world::World,
 };
 
@@ -23,6 +23,7 @@ pub(crate) async fn document_open<E: Environment>(
         context.clone(),
         p.text_document.uri.clone(),
         &p.text_document.text,
+        Some(p.text_document.version),
     )
     .await;
     publish_diagnostics(context.clone(), p.text_document.uri).await;
@@ -49,7 +50,13 @@ pub(crate) async fn document_change<E: Environment>(
         Some(c) => c,
     };
 
-    update_document(context.clone(), p.text_document.uri.clone(), &change.text).await;
+    update_document(
+        context.clone(),
+        p.text_document.uri.clone(),
+        &change.text,
+        Some(p.text_document.version),
+    )
+    .await;
     publish_diagnostics(context.clone(), p.text_document.uri).await;
 
     context
@@ -68,16 +75,103 @@ pub(crate) async fn document_save<E: Environment>(
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn document_close<E: Environment>(
-    _context: Context<World<E>>,
-    _params: Params<DidCloseTextDocumentParams>,
+    context: Context<World<E>>,
+    params: Params<DidCloseTextDocumentParams>,
 ) {
-    // no-op, we track it until it is deleted.
+    let p = match params.optional() {
+        None => return,
+        Some(p) => p,
+    };
+
+    let document_url = p.text_document.uri;
+
+    // Unsaved changes are discarded, the document is reloaded from disk
+    // if it is one of the workspace files, otherwise we stop tracking it.
+    let text = if context
+        .workspaces
+        .snapshot()
+        .by_document(&document_url)
+        .is_source(&document_url)
+    {
+        read_document(&context.env, &document_url).await
+    } else {
+        None
+    };
+
+    {
+        let _pending = context.pending_changes.begin(&document_url);
+
+        let mut workspaces = context.workspaces.write().await;
+        let ws = workspaces.by_document_mut(&document_url);
+
+        match &text {
+            Some(text) => ws.add_document(document_url.clone(), text, None),
+            None => ws.remove_document(&document_url),
+        }
+
+        ws.resolve_all();
+    }
+
+    if text.is_some() {
+        publish_diagnostics(context.clone(), document_url).await;
+    } else {
+        clear_diagnostics(context.clone(), document_url).await;
+    }
+
+    context
+        .clone()
+        .all_diagnostics_debouncer
+        .spawn(publish_all_diagnostics(context));
 }
 
+/// Parses the document and resolves the workspace,
+/// unless the change is older than the loaded document.
 #[tracing::instrument(skip_all)]
-pub(crate) async fn update_document<E: Environment>(ctx: Context<World<E>>, uri: Url, text: &str) {
+pub(crate) async fn update_document<E: Environment>(
+    ctx: Context<World<E>>,
+    uri: Url,
+    text: &str,
+    version: Option<i32>,
+) {
+    // Requests for the document are answered with `ContentModified`
+    // until the change is applied.
+    let _pending = ctx.pending_changes.begin(&uri);
+
     let mut ws = ctx.workspaces.write().await;
     let ws = ws.by_document_mut(&uri);
-    ws.add_document(uri, text);
-    ws.hir.resolve_all();
+
+    if ws.is_outdated_change(&uri, version) {
+        tracing::debug!(%uri, ?version, "ignoring outdated change");
+        return;
+    }
+
+    ws.add_document(uri, text, version);
+    ws.resolve_all();
+}
+
+/// Reads the document from disk.
+pub(crate) async fn read_document<E: Environment>(env: &E, uri: &Url) -> Option<String> {
+    let path = match env.url_to_file_path(uri) {
+        Some(p) => p,
+        None => {
+            tracing::warn!(url = %uri, "could not create file path from url");
+            return None;
+        }
+    };
+
+    let file_content = match env.read_file(&path).await {
+        Ok(c) => c,
+        Err(err) => {
+            tracing::error!(error = %err, "failed to read file");
+            return None;
+        }
+    };
+
+    match String::from_utf8(file_content) {
+        Ok(s) => Some(s),
+        Err(error) => {
+            tracing::error!(url = %uri, %error, "source is not valid UTF-8");
+            None
+        }
+    }
 }
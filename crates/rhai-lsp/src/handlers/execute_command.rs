@@ -0,0 +1,254 @@
+use crate::{diagnostics::publish_diagnostics, world::World};
+use lsp_async_stub::{rpc, Context, Params, RequestWriter};
+use lsp_types::{notification, ExecuteCommandParams, MessageType, ShowMessageParams};
+use rhai_common::{environment::Environment, util::Normalize};
+use serde_json::Value;
+use url::Url;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    diagnostics::{clear_diagnostics, document_diagnostics},
+    run::{run_script, RunEvent},
+    world::Document,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use lsp_async_stub::util::LspExt;
+#[cfg(not(target_arch = "wasm32"))]
+use lsp_types::{Diagnostic, DiagnosticSeverity, LogMessageParams, PublishDiagnosticsParams};
+#[cfg(not(target_arch = "wasm32"))]
+use rhai_rowan::{syntax::SyntaxKind, TextRange, TextSize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{path::Path, sync::atomic::Ordering};
+
+/// Runs the script of the document given as the only argument.
+pub(crate) const RUN_COMMAND: &str = "rhai.run";
+
+/// Checks the document given as the only argument
+/// and publishes its diagnostics.
+pub(crate) const CHECK_COMMAND: &str = "rhai.check";
+
+pub(crate) async fn execute_command<E: Environment>(
+    context: Context<World<E>>,
+    params: Params<ExecuteCommandParams>,
+) -> Result<Option<Value>, rpc::Error> {
+    let p = params.required()?;
+
+    let document_url = match p
+        .arguments
+        .first()
+        .and_then(Value::as_str)
+        .and_then(|url| url.parse::<Url>().ok())
+    {
+        Some(url) => url,
+        None => return Err(rpc::Error::invalid_params()),
+    };
+
+    match p.command.as_str() {
+        CHECK_COMMAND => {
+            check(context, document_url).await;
+            Ok(None)
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        RUN_COMMAND => {
+            run(context, document_url).await?;
+            Ok(None)
+        }
+        #[cfg(target_arch = "wasm32")]
+        RUN_COMMAND => Err(rpc::Error::new(
+            "running scripts is not supported in this environment",
+        )),
+        _ => Err(rpc::Error::invalid_params()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn run<E: Environment>(
+    mut context: Context<World<E>>,
+    document_url: Url,
+) -> Result<(), rpc::Error> {
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&document_url);
+    let doc = ws.document(&document_url)?;
+
+    let script = doc.parse.clone_syntax().to_string();
+    let version = doc.version;
+    let base_dir = ws
+        .env
+        .url_to_file_path(&document_url)
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    drop(workspaces);
+
+    let file_name = file_name(&document_url);
+    log_message(
+        &mut context,
+        MessageType::INFO,
+        format!("Running {file_name}..."),
+    )
+    .await;
+
+    let mut events = run_script(script, base_dir);
+
+    while let Some(event) = events.recv().await {
+        match event {
+            RunEvent::Output(output) => log_message(&mut context, MessageType::LOG, output).await,
+            RunEvent::Finished(Ok(value)) => {
+                // Clear any errors from previous runs.
+                if context.pull_diagnostics.load(Ordering::SeqCst) {
+                    clear_diagnostics(context.clone(), document_url.clone()).await;
+                } else {
+                    publish_diagnostics(context.clone(), document_url.clone()).await;
+                }
+
+                let message = if value.is_empty() {
+                    format!("{file_name} finished.")
+                } else {
+                    format!("{file_name} finished with {value}.")
+                };
+                log_message(&mut context, MessageType::INFO, message).await;
+            }
+            RunEvent::Finished(Err(err)) => {
+                log_message(
+                    &mut context,
+                    MessageType::ERROR,
+                    format!("{file_name} failed: {}", err.message),
+                )
+                .await;
+
+                let workspaces = context.workspaces.snapshot();
+                let ws = workspaces.by_document(&document_url);
+
+                let doc = match ws.documents.get(&document_url) {
+                    Some(doc) => doc,
+                    None => return Ok(()),
+                };
+
+                // The position of the error is meaningless
+                // if the document was edited during the run.
+                if doc.version != version || context.pending_changes.contains(&document_url) {
+                    return Ok(());
+                }
+
+                // Pulled diagnostics are shown separately by the client.
+                let mut diags = if context.pull_diagnostics.load(Ordering::SeqCst) {
+                    Vec::new()
+                } else {
+                    document_diagnostics(&document_url, doc, &ws.hir)
+                };
+                diags.push(Diagnostic {
+                    range: runtime_error_range(doc, err.position),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: None,
+                    code_description: None,
+                    source: Some("Rhai".into()),
+                    message: err.message,
+                    related_information: None,
+                    tags: None,
+                    data: None,
+                });
+                drop(workspaces);
+
+                context
+                    .write_notification::<notification::PublishDiagnostics, _>(Some(
+                        PublishDiagnosticsParams {
+                            uri: document_url.clone(),
+                            diagnostics: diags,
+                            version,
+                        },
+                    ))
+                    .await
+                    .unwrap_or_else(|err| tracing::error!("{err}"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Maps the 1-based line and character column reported by
+/// the engine to the range of the token at that position.
+#[cfg(not(target_arch = "wasm32"))]
+fn runtime_error_range(doc: &Document, position: Option<(usize, usize)>) -> lsp_types::Range {
+    let (line, column) = match position {
+        Some(p) => p,
+        None => return lsp_types::Range::default(),
+    };
+
+    let syntax = doc.parse.clone_syntax();
+    let text = syntax.to_string();
+
+    let line_start = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+
+    let column_offset = text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(text.len() - line_start, |(idx, _)| idx);
+
+    let offset = TextSize::try_from(line_start + column_offset).unwrap_or_default();
+
+    let range = syntax
+        .token_at_offset(offset)
+        .right_biased()
+        .filter(|token| token.kind() != SyntaxKind::WHITESPACE)
+        .map_or_else(|| TextRange::empty(offset), |token| token.text_range());
+
+    doc.mapper.range(range).unwrap_or_default().into_lsp()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn log_message<E: Environment>(
+    context: &mut Context<World<E>>,
+    typ: MessageType,
+    message: String,
+) {
+    context
+        .write_notification::<notification::LogMessage, _>(Some(LogMessageParams { typ, message }))
+        .await
+        .unwrap_or_else(|err| tracing::error!("{err}"));
+}
+
+fn file_name(document_url: &Url) -> String {
+    document_url
+        .path_segments()
+        .and_then(Iterator::last)
+        .unwrap_or_default()
+        .to_string()
+}
+
+async fn check<E: Environment>(mut context: Context<World<E>>, document_url: Url) {
+    publish_diagnostics(context.clone(), document_url.clone()).await;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&document_url);
+
+    let syntax_errors = ws
+        .documents
+        .get(&document_url)
+        .map_or(0, |doc| doc.parse.errors.len());
+
+    let hir_errors = ws
+        .hir
+        .source_of(&document_url.clone().normalize())
+        .map_or(0, |source| ws.hir.errors_for_source(source).len());
+
+    drop(workspaces);
+
+    let file_name = file_name(&document_url);
+
+    let message = match syntax_errors + hir_errors {
+        0 => format!("No problems found in {file_name}."),
+        1 => format!("Found 1 problem in {file_name}."),
+        count => format!("Found {count} problems in {file_name}."),
+    };
+
+    context
+        .write_notification::<notification::ShowMessage, _>(Some(ShowMessageParams {
+            typ: MessageType::INFO,
+            message,
+        }))
+        .await
+        .unwrap_or_else(|err| tracing::error!("{err}"));
+}
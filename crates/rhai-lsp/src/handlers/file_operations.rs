@@ -0,0 +1,188 @@
+use crate::{
+    diagnostics::{clear_diagnostics, publish_all_diagnostics},
+    utils::import_path_of,
+    world::{WorkspaceSnapshot, World},
+};
+use lsp_async_stub::{rpc, util::LspExt, Context, Params};
+use lsp_types::{RenameFilesParams, TextEdit, Url, WorkspaceEdit};
+use rhai_common::{environment::Environment, util::Normalize};
+use rhai_hir::symbol::SymbolKind;
+use std::collections::HashMap;
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn will_rename_files<E: Environment>(
+    context: Context<World<E>>,
+    params: Params<RenameFilesParams>,
+) -> Result<Option<WorkspaceEdit>, rpc::Error> {
+    let p = params.required()?;
+    let renames = file_renames(&p);
+
+    let workspaces = context.workspaces.snapshot();
+
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+
+    for (_, ws) in workspaces.iter() {
+        import_path_edits(ws, &renames, &mut changes);
+    }
+
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }))
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn did_rename_files<E: Environment>(
+    context: Context<World<E>>,
+    params: Params<RenameFilesParams>,
+) {
+    let p = match params.optional() {
+        Some(p) => p,
+        None => return,
+    };
+    let renames = file_renames(&p);
+
+    let mut workspaces = context.workspaces.write().await;
+
+    let mut old_urls = Vec::new();
+
+    for (old_url, _) in &renames {
+        let ws = workspaces.by_document_mut(old_url);
+
+        let moved = ws
+            .documents
+            .keys()
+            .filter_map(|url| renamed_url(url, &renames).map(|new_url| (url.clone(), new_url)))
+            .collect::<Vec<_>>();
+
+        if moved.is_empty() {
+            continue;
+        }
+
+        for (url, new_url) in moved {
+            ws.rename_document(&url, new_url);
+            old_urls.push(url);
+        }
+
+        ws.resolve_all();
+    }
+
+    drop(workspaces);
+
+    for url in old_urls {
+        clear_diagnostics(context.clone(), url).await;
+    }
+
+    context
+        .clone()
+        .all_diagnostics_debouncer
+        .spawn(publish_all_diagnostics(context));
+}
+
+fn file_renames(params: &RenameFilesParams) -> Vec<(Url, Url)> {
+    params
+        .files
+        .iter()
+        .filter_map(|file| Some((file.old_uri.parse().ok()?, file.new_uri.parse().ok()?)))
+        .collect()
+}
+
+/// The new URL of the document if it was renamed,
+/// either directly or as part of a renamed directory.
+fn renamed_url(url: &Url, renames: &[(Url, Url)]) -> Option<Url> {
+    renames.iter().find_map(|(old_url, new_url)| {
+        if url == old_url {
+            return Some(new_url.clone());
+        }
+
+        let rest = url
+            .as_str()
+            .strip_prefix(old_url.as_str().trim_end_matches('/'))?
+            .strip_prefix('/')?;
+
+        format!("{}/{rest}", new_url.as_str().trim_end_matches('/'))
+            .parse()
+            .ok()
+    })
+}
+
+/// Collects edits for relative import paths that will no longer
+/// point to the same module after the renames, this includes
+/// imports in the renamed documents themselves.
+fn import_path_edits<E: Environment>(
+    ws: &WorkspaceSnapshot<E>,
+    renames: &[(Url, Url)],
+    changes: &mut HashMap<Url, Vec<TextEdit>>,
+) {
+    let moved = ws
+        .documents
+        .keys()
+        .filter_map(|url| {
+            renamed_url(url, renames).map(|new_url| (url.clone().normalize(), new_url.normalize()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    if moved.is_empty() {
+        return;
+    }
+
+    for (document_url, doc) in &ws.documents {
+        let from_url = document_url.clone().normalize();
+
+        let source = match ws.hir.source_of(&from_url) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let new_from_url = moved.get(&from_url).unwrap_or(&from_url);
+
+        for (_, data) in ws.hir.source_symbols(source) {
+            let import = match &data.kind {
+                SymbolKind::Import(import) => import,
+                _ => continue,
+            };
+
+            let import_path = match import.import_path(&ws.hir) {
+                Some(path) if path.starts_with('.') => path,
+                _ => continue,
+            };
+
+            let module_url = match ws.hir.resolve_import_url(Some(&from_url), import_path) {
+                Some(url) => url,
+                None => continue,
+            };
+
+            let new_module_url = moved.get(&module_url).unwrap_or(&module_url);
+
+            if new_from_url == &from_url && new_module_url == &module_url {
+                continue;
+            }
+
+            let new_import_path = match import_path_of(new_from_url, new_module_url) {
+                Some(path) if path != import_path => path,
+                _ => continue,
+            };
+
+            let range = match import
+                .expr
+                .and_then(|expr| ws.hir[expr].text_range())
+                .and_then(|range| doc.mapper.range(range))
+            {
+                Some(range) => range,
+                None => continue,
+            };
+
+            changes
+                .entry(document_url.clone())
+                .or_default()
+                .push(TextEdit {
+                    range: range.into_lsp(),
+                    new_text: format!("\"{new_import_path}\""),
+                });
+        }
+    }
+}
@@ -1,22 +1,24 @@
-use crate::world::World;
+use crate::{mapper::Mapper, utils::check_request, world::World};
 use rhai_common::environment::Environment;
 
 use az::SaturatingAs;
 use lsp_async_stub::{
     rpc,
-    util::{LspExt, Mapper},
+    util::LspExt,
     Context, Params,
 };
 use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams, Range};
 use rhai_rowan::syntax::{SyntaxElement, SyntaxKind::*, SyntaxNode};
 
 pub(crate) async fn folding_ranges<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<FoldingRangeParams>,
 ) -> Result<Option<Vec<FoldingRange>>, rpc::Error> {
     let p = params.required()?;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&p.text_document.uri);
 
     let doc = ws.document(&p.text_document.uri)?;
@@ -1,12 +1,19 @@
-use crate::world::World;
+use crate::{
+    utils::check_request,
+    world::{WorkspaceSnapshot, World},
+};
 use rhai_common::{environment::Environment, util::Normalize};
 
 use lsp_async_stub::{rpc, util::LspExt, Context, Params};
 use lsp_types::{
     request::{GotoDeclarationParams, GotoDeclarationResponse},
-    GotoDefinitionParams, GotoDefinitionResponse, LocationLink, Position, Url,
+    request::{GotoTypeDefinitionParams, GotoTypeDefinitionResponse},
+    GotoDefinitionParams, GotoDefinitionResponse, LocationLink, Position, Range, Url,
+};
+use rhai_hir::{
+    symbol::{ReferenceTarget, SymbolKind},
+    Symbol,
 };
-use rhai_hir::symbol::ReferenceTarget;
 
 pub(crate) async fn goto_declaration<E: Environment>(
     context: Context<World<E>>,
@@ -38,11 +45,13 @@ pub(crate) async fn goto_definition<E: Environment>(
 }
 
 async fn goto_target<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     uri: Url,
     pos: Position,
 ) -> Result<Option<Vec<LocationLink>>, rpc::Error> {
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&uri);
 
     let doc = ws.document(&uri)?;
@@ -70,44 +79,9 @@ async fn goto_target<E: Environment>(
             .selection_or_text_range()
             .and_then(|range| doc.mapper.range(range).map(LspExt::into_lsp));
         match &data.kind {
-            rhai_hir::symbol::SymbolKind::Reference(r) => {
+            SymbolKind::Reference(r) => {
                 if let Some(ReferenceTarget::Symbol(target)) = &r.target {
-                    let target_data = &ws.hir[*target];
-
-                    let target_source = match target_data.source.source {
-                        Some(s) => s,
-                        None => return Ok(None),
-                    };
-
-                    let target_source_data = &ws.hir[target_source];
-
-                    let target_document = match ws.documents.get(&target_source_data.url) {
-                        Some(d) => d,
-                        None => return Ok(None),
-                    };
-
-                    let target_range = match target_data
-                        .text_range()
-                        .and_then(|range| target_document.mapper.range(range).map(LspExt::into_lsp))
-                    {
-                        Some(range) => range,
-                        None => return Ok(None),
-                    };
-
-                    let target_selection_range = target_data
-                        .selection_range()
-                        .and_then(|range| target_document.mapper.range(range).map(LspExt::into_lsp))
-                        .unwrap_or(target_range);
-
-                    return Ok(Some(vec![LocationLink {
-                        origin_selection_range,
-                        target_uri: target_data
-                            .source
-                            .source
-                            .map_or(uri, |s| ws.hir[s].url.clone()),
-                        target_range,
-                        target_selection_range,
-                    }]));
+                    return Ok(location_link(ws, *target, origin_selection_range).map(|l| vec![l]));
                 }
             }
             _ => {}
@@ -116,3 +90,97 @@ async fn goto_target<E: Environment>(
 
     Ok(None)
 }
+
+pub(crate) async fn goto_type_definition<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<GotoTypeDefinitionParams>,
+) -> Result<Option<GotoTypeDefinitionResponse>, rpc::Error> {
+    let p = params.required()?;
+
+    let uri = p.text_document_position_params.text_document.uri;
+    let pos = p.text_document_position_params.position;
+
+    check_request(&mut context, &uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&uri);
+
+    let doc = ws.document(&uri)?;
+
+    let offset = match doc
+        .mapper
+        .offset(lsp_async_stub::util::Position::from_lsp(pos))
+    {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    let source = match ws.hir.source_of(&uri.clone().normalize()) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let symbol = match ws.hir.symbol_selection_at(source, offset, true) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let data = &ws.hir[symbol];
+
+    let origin_selection_range = data
+        .selection_or_text_range()
+        .and_then(|range| doc.mapper.range(range).map(LspExt::into_lsp));
+
+    let ty = match &data.kind {
+        // Fields are typed by the field access expression.
+        SymbolKind::Reference(r) if r.field_access => ws
+            .hir
+            .scope(data.parent_scope)
+            .and_then(|scope| scope.parent)
+            .and_then(|parent| parent.as_symbol().copied())
+            .map_or(data.ty, |binary| ws.hir[binary].ty),
+        SymbolKind::TypeDecl(_) => return Ok(None),
+        _ => data.ty,
+    };
+
+    let links = ws
+        .hir
+        .type_decls_of(ty)
+        .into_iter()
+        .filter_map(|decl| location_link(ws, decl, origin_selection_range))
+        .collect::<Vec<_>>();
+
+    if links.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(GotoTypeDefinitionResponse::Link(links)))
+}
+
+fn location_link<E: Environment>(
+    ws: &WorkspaceSnapshot<E>,
+    target: Symbol,
+    origin_selection_range: Option<Range>,
+) -> Option<LocationLink> {
+    let target_data = &ws.hir[target];
+
+    let target_source_data = &ws.hir[target_data.source.source?];
+
+    let target_document = ws.documents.get(&target_source_data.url)?;
+
+    let target_range = target_data
+        .text_range()
+        .and_then(|range| target_document.mapper.range(range).map(LspExt::into_lsp))?;
+
+    let target_selection_range = target_data
+        .selection_range()
+        .and_then(|range| target_document.mapper.range(range).map(LspExt::into_lsp))
+        .unwrap_or(target_range);
+
+    Some(LocationLink {
+        origin_selection_range,
+        target_uri: target_source_data.url.clone(),
+        target_range,
+        target_selection_range,
+    })
+}
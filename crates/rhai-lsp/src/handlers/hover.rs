@@ -1,4 +1,7 @@
-use crate::{utils::documentation_for, world::World};
+use crate::{
+    utils::{check_request, documentation_for},
+    world::World,
+};
 use lsp_async_stub::{rpc, util::LspExt, Context, Params};
 use lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind, Range};
 use rhai_common::{environment::Environment, util::Normalize};
@@ -6,7 +9,7 @@ use rhai_hir::{symbol::ReferenceTarget, Hir, Symbol};
 use rhai_rowan::{query::Query, syntax::SyntaxNode, TextSize};
 
 pub(crate) async fn hover<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<HoverParams>,
 ) -> Result<Option<Hover>, rpc::Error> {
     let p = params.required()?;
@@ -14,7 +17,9 @@ pub(crate) async fn hover<E: Environment>(
     let uri = p.text_document_position_params.text_document.uri;
     let pos = p.text_document_position_params.position;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&uri);
 
     let doc = ws.document(&uri)?;
@@ -86,6 +91,14 @@ fn hover_for_symbol(
             })
         }
         rhai_hir::symbol::SymbolKind::Reference(r) => match &r.target {
+            // Show the type of the variable at the reference.
+            Some(ReferenceTarget::Symbol(target)) if hir[*target].kind.is_decl() => Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: documentation_for(hir, symbol, true),
+                }),
+                range: highlight_range,
+            }),
             Some(ReferenceTarget::Symbol(target)) => {
                 hover_for_symbol(hir, root, highlight_range, *target)
             }
@@ -1,19 +1,29 @@
-use super::{semantic_tokens, update_configuration};
+use super::{
+    load_workspace_files, semantic_tokens, update_configuration, CHECK_COMMAND, RUN_COMMAND,
+};
 use crate::{
     config::InitConfig,
     diagnostics::publish_all_diagnostics,
+    lsp_ext::request::{DiagnosticRegistrationOptions, DocumentDiagnostic, InitializeParams},
+    mapper::PositionEncoding,
     world::{Workspace, DEFAULT_WORKSPACE_URL},
     World,
 };
-use lsp_async_stub::{rpc::Error, Context, Params};
+use anyhow::Context as AnyhowContext;
+use lsp_async_stub::{rpc::Error, Context, Params, RequestWriter};
 use lsp_types::{
-    CompletionOptions, DeclarationCapability, FoldingRangeProviderCapability,
-    HoverProviderCapability, InitializedParams, OneOf, RenameOptions, SemanticTokensFullOptions,
-    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensServerCapabilities,
-    ServerCapabilities, ServerInfo, TextDocumentSyncCapability, TextDocumentSyncKind,
-    WorkDoneProgressOptions, WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
+    request::{RegisterCapability, Request},
+    CodeLensOptions, CompletionOptions, DeclarationCapability, DocumentFilter, DocumentLinkOptions,
+    ExecuteCommandOptions, FileOperationFilter, FileOperationPattern, FileOperationPatternKind,
+    FileOperationRegistrationOptions, FoldingRangeProviderCapability, HoverProviderCapability,
+    InitializedParams, OneOf, Registration, RegistrationParams, RenameOptions,
+    SelectionRangeProviderCapability, SemanticTokensFullOptions, SemanticTokensLegend,
+    SemanticTokensOptions, SemanticTokensServerCapabilities, ServerCapabilities, ServerInfo,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TypeDefinitionProviderCapability,
+    WorkDoneProgressOptions, WorkspaceFileOperationsServerCapabilities,
+    WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
 };
-use lsp_types::{InitializeParams, InitializeResult};
+use lsp_types::InitializeResult;
 use rhai_common::environment::Environment;
 use std::sync::Arc;
 
@@ -22,7 +32,26 @@ pub async fn initialize<E: Environment>(
     context: Context<World<E>>,
     params: Params<InitializeParams>,
 ) -> Result<InitializeResult, Error> {
-    let p = params.required()?;
+    let InitializeParams {
+        params: p,
+        capabilities,
+    } = params.required()?;
+
+    context
+        .client_capabilities
+        .store(Arc::new(p.capabilities.clone()));
+    context
+        .raw_client_capabilities
+        .store(Arc::new(capabilities));
+
+    let position_encoding = PositionEncoding::negotiate(
+        p.capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_deref()),
+    );
+    tracing::info!(?position_encoding, "negotiated position encoding");
+    context.position_encoding.store(Arc::new(position_encoding));
 
     if let Some(init_opts) = p.initialization_options {
         match serde_json::from_value::<InitConfig>(init_opts) {
@@ -33,23 +62,35 @@ pub async fn initialize<E: Environment>(
         }
     }
 
-    if let Some(workspaces) = p.workspace_folders {
-        let mut wss = context.workspaces.write().await;
+    let mut wss = context.workspaces.write().await;
 
-        for workspace in workspaces {
-            wss.entry(workspace.uri.clone())
-                .or_insert(Workspace::new(context.env.clone(), workspace.uri));
-        }
+    for (_, ws) in wss.iter_mut() {
+        ws.position_encoding = position_encoding;
+    }
+
+    for workspace in p.workspace_folders.into_iter().flatten() {
+        wss.add(Workspace::new(
+            context.env.clone(),
+            workspace.uri,
+            position_encoding,
+        ));
     }
 
+    drop(wss);
+
     Ok(InitializeResult {
         capabilities: ServerCapabilities {
+            position_encoding: Some(position_encoding.kind()),
             workspace: Some(WorkspaceServerCapabilities {
                 workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                     supported: Some(true),
                     change_notifications: Some(OneOf::Left(true)),
                 }),
-                ..Default::default()
+                file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                    will_rename: Some(rename_file_options()),
+                    did_rename: Some(rename_file_options()),
+                    ..Default::default()
+                }),
             }),
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
             rename_provider: Some(OneOf::Right(RenameOptions {
@@ -57,10 +98,24 @@ pub async fn initialize<E: Environment>(
                 work_done_progress_options: Default::default(),
             })),
             folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
             references_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
             declaration_provider: Some(DeclarationCapability::Simple(true)),
             definition_provider: Some(OneOf::Left(true)),
+            type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
             document_symbol_provider: Some(OneOf::Left(true)),
+            document_link_provider: Some(DocumentLinkOptions {
+                resolve_provider: Some(false),
+                work_done_progress_options: Default::default(),
+            }),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(true),
+            }),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: vec![RUN_COMMAND.into(), CHECK_COMMAND.into()],
+                work_done_progress_options: Default::default(),
+            }),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
             semantic_tokens_provider: Some(
                 SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
@@ -71,13 +126,20 @@ pub async fn initialize<E: Environment>(
                         token_types: semantic_tokens::TokenType::LEGEND.into(),
                         token_modifiers: semantic_tokens::TokenModifier::MODIFIERS.into(),
                     },
-                    full: Some(SemanticTokensFullOptions::Bool(true)),
-                    range: Some(false),
+                    full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                    range: Some(true),
                 }),
             ),
             completion_provider: Some(CompletionOptions {
-                resolve_provider: Some(false),
-                trigger_characters: Some(vec!["#".into(), "=".into(), ".".into(), ":".into()]),
+                resolve_provider: Some(true),
+                trigger_characters: Some(vec![
+                    "#".into(),
+                    "=".into(),
+                    ".".into(),
+                    ":".into(),
+                    "/".into(),
+                    "\"".into(),
+                ]),
                 ..CompletionOptions::default()
             }),
             ..Default::default()
@@ -86,18 +148,46 @@ pub async fn initialize<E: Environment>(
             name: "Rhai Language Server".into(),
             version: Some(env!("CARGO_PKG_VERSION").into()),
         }),
+        offset_encoding: None,
     })
 }
 
+/// Rhai scripts and definitions, and folders that might contain them.
+fn rename_file_options() -> FileOperationRegistrationOptions {
+    FileOperationRegistrationOptions {
+        filters: vec![
+            FileOperationFilter {
+                scheme: Some("file".into()),
+                pattern: FileOperationPattern {
+                    glob: "**/*.rhai".into(),
+                    matches: Some(FileOperationPatternKind::File),
+                    options: None,
+                },
+            },
+            FileOperationFilter {
+                scheme: Some("file".into()),
+                pattern: FileOperationPattern {
+                    glob: "**".into(),
+                    matches: Some(FileOperationPatternKind::Folder),
+                    options: None,
+                },
+            },
+        ],
+    }
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn initialized<E: Environment>(
     context: Context<World<E>>,
     _params: Params<InitializedParams>,
 ) {
     update_configuration(context.clone()).await;
+    register_pull_diagnostics(context.clone()).await;
 
     let mut workspaces = context.workspaces.write().await;
 
+    let mut ws_urls = Vec::new();
+
     for (ws_url, ws) in workspaces.iter_mut() {
         if ws_url == &*DEFAULT_WORKSPACE_URL {
             continue;
@@ -107,12 +197,53 @@ pub async fn initialized<E: Environment>(
             tracing::error!(%error, "failed to load Rhai config");
         }
 
-        ws.load_all_files().await;
+        ws_urls.push(ws_url.clone());
     }
 
     drop(workspaces);
+
+    for ws_url in ws_urls {
+        load_workspace_files(context.clone(), ws_url).await;
+    }
+
     context
         .clone()
         .all_diagnostics_debouncer
         .spawn(publish_all_diagnostics(context));
 }
+
+/// Offers pull diagnostics to the client, diagnostics are
+/// still published until the client starts pulling them.
+async fn register_pull_diagnostics<E: Environment>(mut context: Context<World<E>>) {
+    if !context.client_supports("/textDocument/diagnostic/dynamicRegistration") {
+        tracing::debug!("pull diagnostics are not supported");
+        return;
+    }
+
+    let options = DiagnosticRegistrationOptions {
+        document_selector: Some(vec![DocumentFilter {
+            language: Some("rhai".into()),
+            scheme: None,
+            pattern: None,
+        }]),
+        identifier: None,
+        inter_file_dependencies: true,
+        workspace_diagnostics: true,
+    };
+
+    let registered = context
+        .write_request::<RegisterCapability, _>(Some(RegistrationParams {
+            registrations: vec![Registration {
+                id: DocumentDiagnostic::METHOD.into(),
+                method: DocumentDiagnostic::METHOD.into(),
+                register_options: serde_json::to_value(options).ok(),
+            }],
+        }))
+        .await
+        .context("failed to register pull diagnostics")
+        .and_then(|res| res.into_result().context("invalid registration response"));
+
+    if let Err(error) = registered {
+        tracing::debug!(?error, "pull diagnostics are not supported");
+    }
+}
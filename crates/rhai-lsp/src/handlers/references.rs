@@ -1,22 +1,26 @@
-use rhai_common::environment::Environment;
 use crate::{
-    world::{Workspace, World},
+    lsp_ext::request::{ReferenceAccess, ReferencesParams},
+    utils::{check_request, RequestCheckpoint},
+    world::{WorkspaceSnapshot, World},
 };
 use lsp_async_stub::{rpc, util::LspExt, Context, Params};
-use lsp_types::{Location, ReferenceParams};
-use rhai_hir::Symbol;
+use lsp_types::Location;
+use rhai_common::environment::Environment;
+use rhai_hir::{symbol::SymbolAccess, Symbol};
 use rhai_rowan::{syntax::SyntaxKind, TextRange, TextSize};
 
 pub(crate) async fn references<E: Environment>(
-    context: Context<World<E>>,
-    params: Params<ReferenceParams>,
+    mut context: Context<World<E>>,
+    params: Params<ReferencesParams>,
 ) -> Result<Option<Vec<Location>>, rpc::Error> {
-    let p = params.required()?;
+    let ReferencesParams { params: p, access } = params.required()?;
 
     let uri = p.text_document_position.text_document.uri;
     let pos = p.text_document_position.position;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&uri);
 
     let doc = ws.document(&uri)?;
@@ -38,24 +42,43 @@ pub(crate) async fn references<E: Environment>(
         return Ok(None);
     }
 
-    let target_symbol = ws
-        .hir
-        .symbols()
-        .find(|(_, symbol)| symbol.has_selection_range(elem.text_range()));
+    let mut checkpoint = RequestCheckpoint::new(&context);
+    let mut target_symbol = None;
+
+    for (symbol, data) in ws.hir.symbols() {
+        checkpoint.check(&mut context, &uri).await?;
 
-    if let Some((sym, _)) = target_symbol {
+        if data.has_selection_range(elem.text_range()) {
+            target_symbol = Some(symbol);
+            break;
+        }
+    }
+
+    if let Some(sym) = target_symbol {
         let mut locations = Vec::new();
-        collect_references(ws, sym, p.context.include_declaration, &mut locations);
+        collect_references(
+            ws,
+            sym,
+            p.context.include_declaration,
+            access.map(|access| match access {
+                ReferenceAccess::Read => SymbolAccess::Read,
+                ReferenceAccess::Write => SymbolAccess::Write,
+            }),
+            &mut locations,
+        );
         return Ok(Some(locations));
     }
 
     Ok(None)
 }
 
+/// Collects the locations of the references to the given symbol,
+/// optionally only the ones that read or write it.
 pub(crate) fn collect_references<E: Environment>(
-    w: &Workspace<E>,
+    w: &WorkspaceSnapshot<E>,
     target_symbol: Symbol,
     include_declaration: bool,
+    access: Option<SymbolAccess>,
     locations: &mut Vec<Location>,
 ) {
     let target_data = &w.hir[target_symbol];
@@ -69,6 +92,9 @@ pub(crate) fn collect_references<E: Environment>(
     locations.extend(
         references
             .iter()
+            .filter(|&&reference| {
+                access.is_none_or(|access| w.hir.symbol_access(reference) == access)
+            })
             .filter_map(|&reference| {
                 let reference_data = &w.hir[reference];
 
@@ -93,7 +119,7 @@ pub(crate) fn collect_references<E: Environment>(
             .map(|(url, range)| Location { uri: url, range }),
     );
 
-    if include_declaration {
+    if include_declaration && access.is_none_or(|access| access == SymbolAccess::Write) {
         let target_data = &w.hir[target_symbol];
 
         let target_source = match target_data.source.source {
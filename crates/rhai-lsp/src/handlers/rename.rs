@@ -1,4 +1,7 @@
-use crate::world::{Workspace, World};
+use crate::{
+    utils::check_request,
+    world::{WorkspaceSnapshot, World},
+};
 use core::iter;
 use lsp_async_stub::{
     rpc::Error,
@@ -9,18 +12,39 @@ use lsp_types::{
     PrepareRenameResponse, RenameParams, TextDocumentPositionParams, TextEdit, Url, WorkspaceEdit,
 };
 use rhai_common::{environment::Environment, util::Normalize};
-use rhai_hir::{symbol, Hir, Symbol};
+use rhai_hir::{
+    source::Source,
+    symbol::{ReferenceTarget, SymbolKind, VirtualSymbol},
+    Hir, Symbol,
+};
+use rhai_rowan::{
+    ast::{AstNode, RhaiDef},
+    util::{is_valid_ident, keyword_kind},
+    TextRange, TextSize,
+};
 use std::collections::HashMap;
 
+/// What is renamed.
+enum RenameTarget {
+    /// A function or a declaration, including import aliases.
+    Symbol(Symbol),
+    /// A static module declared in a definition file.
+    Module(Symbol),
+    /// A field of objects inferred from the same object literal.
+    ObjectField { literal: Symbol, name: String },
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn prepare_rename<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<TextDocumentPositionParams>,
 ) -> Result<Option<PrepareRenameResponse>, Error> {
     let p = params.required()?;
     let document_uri = p.text_document.uri;
 
-    let workspaces = context.workspaces.write().await;
+    check_request(&mut context, &document_uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&document_uri);
     let doc = ws.document(&document_uri)?;
 
@@ -38,37 +62,22 @@ pub async fn prepare_rename<E: Environment>(
         None => return Ok(None),
     };
 
-    let target_symbol = ws
-        .hir
-        .symbol_selection_at(source, offset, true)
-        .map(|s| (s, &ws.hir[s]));
-
-    Ok(target_symbol.and_then(|(_, data)| {
-        let range = data.selection_range().and_then(|r| doc.mapper.range(r));
-
-        match range {
-            Some(range) => match &data.kind {
-                symbol::SymbolKind::Fn(_)
-                | symbol::SymbolKind::Decl(_)
-                | symbol::SymbolKind::Reference(_) => {
-                    Some(PrepareRenameResponse::Range(range.into_lsp()))
-                }
-                _ => None,
-            },
-            None => None,
-        }
-    }))
+    Ok(rename_target(&ws.hir, source, offset)
+        .and_then(|(_, range)| doc.mapper.range(range))
+        .map(|range| PrepareRenameResponse::Range(range.into_lsp())))
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn rename<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<RenameParams>,
 ) -> Result<Option<WorkspaceEdit>, Error> {
     let p = params.required()?;
     let document_uri = p.text_document_position.text_document.uri;
 
-    let workspaces = context.workspaces.write().await;
+    check_request(&mut context, &document_uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&document_uri);
     let doc = ws.document(&document_uri)?;
 
@@ -86,59 +95,279 @@ pub async fn rename<E: Environment>(
         None => return Ok(None),
     };
 
-    let mut target_symbol = ws
-        .hir
-        .symbol_selection_at(source, offset, true)
-        .map(|s| (s, &ws.hir[s]));
-
-    if let Some((sym, data)) = target_symbol.take() {
-        if let symbol::SymbolKind::Reference(r) = &data.kind {
-            match r.target {
-                Some(t) => match t {
-                    symbol::ReferenceTarget::Symbol(ts) => {
-                        target_symbol = Some((ts, &ws.hir[ts]));
+    let target = match rename_target(&ws.hir, source, offset) {
+        Some((target, _)) => target,
+        None => return Ok(None),
+    };
+
+    let new_name = p.new_name.as_str();
+
+    if let Some(kind) = keyword_kind(new_name) {
+        return Err(rename_error(if kind.is_reserved_keyword() {
+            format!("`{new_name}` is a reserved keyword")
+        } else {
+            format!("`{new_name}` is a keyword")
+        }));
+    }
+
+    if !is_valid_ident(new_name) {
+        return Err(rename_error(format!(
+            "`{new_name}` is not a valid identifier"
+        )));
+    }
+
+    let changes = match target {
+        RenameTarget::Symbol(target_symbol) => {
+            let references = match &ws.hir[target_symbol].kind {
+                SymbolKind::Fn(f) => &f.references,
+                SymbolKind::Decl(d) => &d.references,
+                _ => return Ok(None),
+            };
+
+            if let Some(existing) = symbol_clash(&ws.hir, target_symbol, new_name) {
+                return Err(rename_error(existing));
+            }
+
+            rename_symbols(
+                &ws.hir,
+                iter::once(target_symbol).chain(references.iter().copied()),
+                new_name,
+                ws,
+            )
+        }
+        RenameTarget::Module(module_symbol) => {
+            let static_scope = ws.hir[ws.hir.static_module()].scope;
+
+            if ws
+                .hir
+                .scope_symbols(static_scope)
+                .any(|symbol| ws.hir[symbol].name(&ws.hir) == Some(new_name))
+            {
+                return Err(rename_error(format!(
+                    "a module named `{new_name}` already exists"
+                )));
+            }
+
+            let mut changes = rename_symbols(
+                &ws.hir,
+                module_references(&ws.hir, module_symbol),
+                new_name,
+                ws,
+            );
+            rename_module_decls(&ws.hir, module_symbol, new_name, ws, &mut changes);
+            changes
+        }
+        RenameTarget::ObjectField { literal, name } => {
+            let has_field = ws.hir[literal]
+                .kind
+                .as_object()
+                .is_some_and(|o| o.fields.contains_key(new_name));
+
+            if has_field
+                || ws
+                    .hir
+                    .object_field_accesses(literal, new_name)
+                    .next()
+                    .is_some()
+            {
+                return Err(rename_error(format!(
+                    "the object already has a field named `{new_name}`"
+                )));
+            }
+
+            let mut changes = rename_symbols(
+                &ws.hir,
+                ws.hir.object_field_accesses(literal, &name),
+                new_name,
+                ws,
+            );
+
+            if let Some(field) = ws.hir[literal]
+                .kind
+                .as_object()
+                .and_then(|o| o.fields.get(&name))
+            {
+                if let (Some(source), Some(range)) = (
+                    field.property_syntax.source,
+                    field.property_syntax.text_range,
+                ) {
+                    push_edit(&ws.hir, ws, source, range, new_name, &mut changes);
+                }
+            }
+
+            changes
+        }
+    };
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }))
+}
+
+/// Determine what is renamed at the given offset,
+/// and the range of the name that is renamed.
+fn rename_target(hir: &Hir, source: Source, offset: TextSize) -> Option<(RenameTarget, TextRange)> {
+    let symbol = match hir.symbol_selection_at(source, offset, true) {
+        Some(symbol) => symbol,
+        None => return object_literal_field_at(hir, source, offset),
+    };
+
+    let data = &hir[symbol];
+    let range = data.selection_range()?;
+
+    match &data.kind {
+        SymbolKind::Fn(_) | SymbolKind::Decl(_) => Some((RenameTarget::Symbol(symbol), range)),
+        SymbolKind::Reference(r) => {
+            if let Some(access) = hir.field_access_of(symbol) {
+                let lhs = hir[access].kind.as_binary()?.lhs?;
+
+                return Some((
+                    RenameTarget::ObjectField {
+                        literal: hir.object_literal_of(hir[lhs].ty)?,
+                        name: r.name.clone(),
+                    },
+                    range,
+                ));
+            }
+
+            match r.target? {
+                ReferenceTarget::Symbol(target) => match &hir[target].kind {
+                    SymbolKind::Fn(_) | SymbolKind::Decl(_) => {
+                        Some((RenameTarget::Symbol(target), range))
                     }
-                    symbol::ReferenceTarget::Module(_) => {
-                        tracing::warn!("renaming a module is not possible");
+                    SymbolKind::Virtual(VirtualSymbol::Module(_)) => {
+                        Some((RenameTarget::Module(target), range))
                     }
+                    _ => None,
                 },
-                None => {}
+                ReferenceTarget::Module(_) => {
+                    tracing::warn!("renaming a module is not possible");
+                    None
+                }
             }
-        } else {
-            target_symbol = Some((sym, data));
         }
+        _ => None,
     }
+}
+
+/// The property of an object literal at the given offset, e.g. `a` in `#{ a: 1 }`.
+fn object_literal_field_at(
+    hir: &Hir,
+    source: Source,
+    offset: TextSize,
+) -> Option<(RenameTarget, TextRange)> {
+    hir.source_symbols(source).find_map(|(symbol, data)| {
+        data.kind
+            .as_object()?
+            .fields
+            .iter()
+            .find_map(|(name, field)| {
+                let range = field.property_syntax.text_range?;
+
+                range.contains_inclusive(offset).then(|| {
+                    (
+                        RenameTarget::ObjectField {
+                            literal: symbol,
+                            name: name.clone(),
+                        },
+                        range,
+                    )
+                })
+            })
+    })
+}
+
+/// Returns an error message if the new name would clash
+/// with a function or variable that is already visible
+/// at the declaration or any of the references.
+fn symbol_clash(hir: &Hir, target_symbol: Symbol, new_name: &str) -> Option<String> {
+    let references = match &hir[target_symbol].kind {
+        SymbolKind::Fn(f) => &f.references,
+        SymbolKind::Decl(d) => &d.references,
+        _ => return None,
+    };
+
+    let target_params = fn_param_count(hir, target_symbol);
+
+    iter::once(target_symbol)
+        .chain(references.iter().copied())
+        .flat_map(|symbol| hir.visible_symbols_from_symbol(symbol))
+        .filter(|&visible| visible != target_symbol)
+        .filter(|&visible| hir[visible].name(hir) == Some(new_name))
+        // Functions with a different number of parameters
+        // are overloads and do not clash.
+        .find(
+            |&visible| match (target_params, fn_param_count(hir, visible)) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            },
+        )
+        .map(|_| format!("`{new_name}` is already defined"))
+}
+
+fn fn_param_count(hir: &Hir, symbol: Symbol) -> Option<usize> {
+    let f = hir[symbol].kind.as_fn()?;
 
-    Ok(
-        target_symbol.and_then(|(target_symbol, data)| match &data.kind {
-            symbol::SymbolKind::Fn(target) => Some(WorkspaceEdit {
-                changes: Some(rename_symbols(
-                    &ws.hir,
-                    iter::once(target_symbol).chain(target.references.iter().copied()),
-                    &p.new_name,
-                    ws,
-                )),
-                ..Default::default()
-            }),
-            symbol::SymbolKind::Decl(target) => Some(WorkspaceEdit {
-                changes: Some(rename_symbols(
-                    &ws.hir,
-                    iter::once(target_symbol).chain(target.references.iter().copied()),
-                    &p.new_name,
-                    ws,
-                )),
-                ..Default::default()
-            }),
-            _ => None,
-        }),
+    Some(
+        hir.scope_symbols(f.scope)
+            .filter(|&param| hir[param].kind.as_decl().is_some_and(|d| d.is_param))
+            .count(),
     )
 }
 
+/// References to a static module, e.g. `foo` in `foo::bar`.
+fn module_references(hir: &Hir, module_symbol: Symbol) -> impl Iterator<Item = Symbol> + '_ {
+    hir.symbols().filter_map(move |(symbol, data)| match &data.kind {
+        SymbolKind::Reference(r)
+            if matches!(r.target, Some(ReferenceTarget::Symbol(target)) if target == module_symbol) =>
+        {
+            Some(symbol)
+        }
+        _ => None,
+    })
+}
+
+/// Rename the `module name;` declarations in the definitions of a static module.
+fn rename_module_decls<E: Environment>(
+    hir: &Hir,
+    module_symbol: Symbol,
+    new_name: &str,
+    ws: &WorkspaceSnapshot<E>,
+    changes: &mut HashMap<Url, Vec<TextEdit>>,
+) {
+    let module = match &hir[module_symbol].kind {
+        SymbolKind::Virtual(VirtualSymbol::Module(m)) => m.module,
+        _ => return,
+    };
+
+    let module_data = match hir.module(module) {
+        Some(m) => m,
+        None => return,
+    };
+
+    for &source in &module_data.sources {
+        let doc = match ws.document(&hir[source].url) {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+
+        let ident = RhaiDef::cast(doc.parse.clone_syntax())
+            .and_then(|def| def.def_module_decl())
+            .and_then(|decl| decl.def_module())
+            .and_then(|def_mod| def_mod.ident_token());
+
+        if let Some(ident) = ident {
+            push_edit(hir, ws, source, ident.text_range(), new_name, changes);
+        }
+    }
+}
+
 fn rename_symbols<E: Environment>(
     hir: &Hir,
     symbols: impl Iterator<Item = Symbol>,
     new_name: &str,
-    ws: &Workspace<E>,
+    ws: &WorkspaceSnapshot<E>,
 ) -> HashMap<Url, Vec<TextEdit>> {
     let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
 
@@ -155,22 +384,38 @@ fn rename_symbols<E: Environment>(
             None => continue,
         };
 
-        let url = &hir[source].url;
+        push_edit(hir, ws, source, range, new_name, &mut changes);
+    }
 
-        let doc = match ws.document(url) {
-            Ok(d) => d,
-            Err(_) => continue,
-        };
+    changes
+}
 
-        let document_edits = changes.entry(url.clone()).or_default();
+fn push_edit<E: Environment>(
+    hir: &Hir,
+    ws: &WorkspaceSnapshot<E>,
+    source: Source,
+    range: TextRange,
+    new_name: &str,
+    changes: &mut HashMap<Url, Vec<TextEdit>>,
+) {
+    let url = &hir[source].url;
 
-        if let Some(range) = doc.mapper.range(range) {
-            document_edits.push(TextEdit {
-                new_text: new_name.into(),
-                range: range.into_lsp(),
-            });
-        }
+    let doc = match ws.document(url) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+
+    if let Some(range) = doc.mapper.range(range) {
+        changes.entry(url.clone()).or_default().push(TextEdit {
+            new_text: new_name.into(),
+            range: range.into_lsp(),
+        });
     }
+}
 
-    changes
+fn rename_error(message: String) -> Error {
+    Error {
+        message,
+        ..Error::invalid_params()
+    }
 }
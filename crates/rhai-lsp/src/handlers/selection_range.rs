@@ -0,0 +1,61 @@
+use crate::{utils::check_request, world::World};
+use lsp_async_stub::{
+    rpc,
+    util::{LspExt, Position},
+    Context, Params,
+};
+use lsp_types::{SelectionRange, SelectionRangeParams};
+use rhai_common::environment::Environment;
+use rhai_rowan::query::selection_ranges;
+
+pub(crate) async fn selection_range<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<SelectionRangeParams>,
+) -> Result<Option<Vec<SelectionRange>>, rpc::Error> {
+    let p = params.required()?;
+
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&p.text_document.uri);
+
+    let doc = ws.document(&p.text_document.uri)?;
+
+    let syntax = doc.parse.clone_syntax();
+
+    let mut selections = Vec::with_capacity(p.positions.len());
+
+    for position in p.positions {
+        let offset = match doc.mapper.offset(Position::from_lsp(position)) {
+            Some(ofs) => ofs,
+            None => {
+                tracing::error!(?position, "document position not found");
+                return Ok(None);
+            }
+        };
+
+        let mut selection: Option<SelectionRange> = None;
+
+        // Build the chain from the outermost range.
+        for range in selection_ranges(&syntax, offset)
+            .into_iter()
+            .rev()
+            .filter_map(|range| doc.mapper.range(range))
+        {
+            selection = Some(SelectionRange {
+                range: range.into_lsp(),
+                parent: selection.map(Box::new),
+            });
+        }
+
+        // The response must contain a selection for every position.
+        let selection = selection.unwrap_or(SelectionRange {
+            range: lsp_types::Range::new(position, position),
+            parent: None,
+        });
+
+        selections.push(selection);
+    }
+
+    Ok(Some(selections))
+}
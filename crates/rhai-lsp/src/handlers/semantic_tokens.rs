@@ -1,29 +1,141 @@
-use crate::World;
+use crate::{
+    mapper::Mapper,
+    utils::{check_request, RequestCheckpoint},
+    world::Document,
+    World,
+};
 use lsp_async_stub::{
     rpc::Error,
-    util::{relative_range, LspExt, Mapper},
+    util::{relative_range, LspExt, Range},
     Context, Params,
 };
 use lsp_types::{
-    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensParams,
-    SemanticTokensResult,
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensDelta,
+    SemanticTokensDeltaParams, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensParams, SemanticTokensRangeParams, SemanticTokensRangeResult,
+    SemanticTokensResult, Url,
 };
 use rhai_common::environment::Environment;
 use rhai_hir::{
-    symbol::{BinaryOpKind, ReferenceTarget, SymbolKind},
+    source::Source,
+    symbol::{BinaryOpKind, ReferenceTarget, SymbolData, SymbolKind},
     ty::Type,
-    Hir, TypeKind,
+    Hir, Symbol, TypeKind,
 };
 use rhai_rowan::TextRange;
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn semantic_tokens<E: Environment>(
-    context: Context<World<E>>,
+    mut context: Context<World<E>>,
     params: Params<SemanticTokensParams>,
 ) -> Result<Option<SemanticTokensResult>, Error> {
     let p = params.required()?;
 
-    let workspaces = context.workspaces.read().await;
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&p.text_document.uri);
+
+    let doc = ws.document(&p.text_document.uri)?;
+
+    if !ws.config.syntax.semantic_tokens {
+        return Ok(None);
+    }
+
+    let source = match ws.hir.source_by_url(&p.text_document.uri) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let tokens = document_tokens(
+        &mut context,
+        &p.text_document.uri,
+        &ws.hir,
+        doc,
+        source,
+        None,
+    )
+    .await?;
+
+    let mut cache = doc.semantic_tokens.lock().unwrap();
+    cache.result_id += 1;
+    cache.tokens.clone_from(&tokens);
+
+    Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+        result_id: Some(cache.result_id.to_string()),
+        data: tokens,
+    })))
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn semantic_tokens_delta<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<SemanticTokensDeltaParams>,
+) -> Result<Option<SemanticTokensFullDeltaResult>, Error> {
+    let p = params.required()?;
+
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
+    let ws = workspaces.by_document(&p.text_document.uri);
+
+    let doc = ws.document(&p.text_document.uri)?;
+
+    if !ws.config.syntax.semantic_tokens {
+        return Ok(None);
+    }
+
+    let source = match ws.hir.source_by_url(&p.text_document.uri) {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    let tokens = document_tokens(
+        &mut context,
+        &p.text_document.uri,
+        &ws.hir,
+        doc,
+        source,
+        None,
+    )
+    .await?;
+
+    let mut cache = doc.semantic_tokens.lock().unwrap();
+
+    // The client might refer to tokens we no longer have,
+    // in that case all tokens are sent.
+    let edits = if p.previous_result_id == cache.result_id.to_string() {
+        Some(tokens_edits(&cache.tokens, &tokens))
+    } else {
+        None
+    };
+
+    cache.result_id += 1;
+    cache.tokens.clone_from(&tokens);
+
+    let result_id = Some(cache.result_id.to_string());
+
+    Ok(Some(match edits {
+        Some(edits) => {
+            SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta { result_id, edits })
+        }
+        None => SemanticTokensFullDeltaResult::Tokens(SemanticTokens {
+            result_id,
+            data: tokens,
+        }),
+    }))
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn semantic_tokens_range<E: Environment>(
+    mut context: Context<World<E>>,
+    params: Params<SemanticTokensRangeParams>,
+) -> Result<Option<SemanticTokensRangeResult>, Error> {
+    let p = params.required()?;
+
+    check_request(&mut context, &p.text_document.uri)?;
+
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&p.text_document.uri);
 
     let doc = ws.document(&p.text_document.uri)?;
@@ -37,87 +149,159 @@ pub(crate) async fn semantic_tokens<E: Environment>(
         None => return Ok(None),
     };
 
+    let range = match doc.mapper.text_range(Range::from_lsp(p.range)) {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    // Range results are not cached, they are only
+    // used until the full tokens are available.
+    Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+        result_id: None,
+        data: document_tokens(
+            &mut context,
+            &p.text_document.uri,
+            &ws.hir,
+            doc,
+            source,
+            Some(range),
+        )
+        .await?,
+    })))
+}
+
+/// Collects the semantic tokens of a document,
+/// optionally only the ones that overlap the given range.
+async fn document_tokens<E: Environment>(
+    context: &mut Context<World<E>>,
+    document_url: &Url,
+    hir: &Hir,
+    doc: &Document,
+    source: Source,
+    range: Option<TextRange>,
+) -> Result<Vec<SemanticToken>, Error> {
+    let mut checkpoint = RequestCheckpoint::new(context);
     let mut token_builder = SemanticTokensBuilder::new(&doc.mapper);
 
-    token_builder.extend(ws.hir.symbols().filter_map(|(symbol, data)| {
-        if !data.source.is(source) {
-            return None;
+    for (symbol, data) in hir.source_symbols(source) {
+        checkpoint.check(context, document_url).await?;
+
+        let (token_range, ty, modifiers) = match symbol_token(hir, symbol, data) {
+            Some(token) => token,
+            None => continue,
+        };
+
+        if range.is_none_or(|range| {
+            token_range.start() < range.end() && range.start() < token_range.end()
+        }) {
+            token_builder.add(token_range, ty, modifiers);
         }
+    }
 
-        match &data.kind {
-            SymbolKind::Decl(d) => {
-                if let Some(ty) = token_for_ty(&ws.hir, ws.hir[symbol].ty) {
-                    Some((ws.hir[symbol].selection_range()?, ty, vec![]))
-                } else if d.is_const {
-                    Some((
-                        ws.hir[symbol].selection_range()?,
-                        TokenType::Variable,
-                        vec![TokenModifier::ReadOnly],
-                    ))
-                } else {
-                    None
-                }
+    Ok(token_builder.finish())
+}
+
+fn symbol_token(
+    hir: &Hir,
+    symbol: Symbol,
+    data: &SymbolData,
+) -> Option<(TextRange, TokenType, Vec<TokenModifier>)> {
+    match &data.kind {
+        SymbolKind::Decl(d) => {
+            if let Some(ty) = token_for_ty(hir, hir[symbol].ty) {
+                Some((hir[symbol].selection_range()?, ty, vec![]))
+            } else if d.is_const {
+                Some((
+                    hir[symbol].selection_range()?,
+                    TokenType::Variable,
+                    vec![TokenModifier::ReadOnly],
+                ))
+            } else {
+                None
             }
-            SymbolKind::Reference(r) => {
-                if let Some(&target_symbol) = r.target.as_ref().and_then(ReferenceTarget::as_symbol)
-                {
-                    if let Some(ty) = token_for_ty(&ws.hir, ws.hir[target_symbol].ty) {
-                        Some((ws.hir[symbol].selection_range()?, ty, vec![]))
-                    } else if let Some(d) = ws.hir[target_symbol].kind.as_decl() {
-                        if  d.is_const {
-                            Some((
-                                ws.hir[symbol].selection_range()?,
-                                TokenType::Variable,
-                                vec![TokenModifier::ReadOnly],
-                            ))
-                        } else {
-                            None
-                        }
+        }
+        SymbolKind::Reference(r) => {
+            if let Some(&target_symbol) = r.target.as_ref().and_then(ReferenceTarget::as_symbol) {
+                if let Some(ty) = token_for_ty(hir, hir[target_symbol].ty) {
+                    Some((hir[symbol].selection_range()?, ty, vec![]))
+                } else if let Some(d) = hir[target_symbol].kind.as_decl() {
+                    if d.is_const {
+                        Some((
+                            hir[symbol].selection_range()?,
+                            TokenType::Variable,
+                            vec![TokenModifier::ReadOnly],
+                        ))
                     } else {
                         None
                     }
                 } else {
                     None
                 }
+            } else {
+                None
             }
-            SymbolKind::Path(p) => p.segments.last().and_then(|&sym| {
-                if let Some(ty) = token_for_ty(&ws.hir, ws.hir[sym].ty) {
-                    Some((ws.hir[sym].selection_range()?, ty, vec![]))
-                } else if let Some(&target) = ws.hir[sym]
-                    .kind
-                    .as_reference()
-                    .and_then(|r| r.target.as_ref().and_then(ReferenceTarget::as_symbol))
-                {
-                    if let Some(decl) = ws.hir[target].kind.as_decl() {
-                        if decl.is_const {
-                            Some((
-                                ws.hir[sym].selection_range()?,
-                                TokenType::Variable,
-                                vec![TokenModifier::ReadOnly],
-                            ))
-                        } else {
-                            None
-                        }
+        }
+        SymbolKind::Path(p) => p.segments.last().and_then(|&sym| {
+            if let Some(ty) = token_for_ty(hir, hir[sym].ty) {
+                Some((hir[sym].selection_range()?, ty, vec![]))
+            } else if let Some(&target) = hir[sym]
+                .kind
+                .as_reference()
+                .and_then(|r| r.target.as_ref().and_then(ReferenceTarget::as_symbol))
+            {
+                if let Some(decl) = hir[target].kind.as_decl() {
+                    if decl.is_const {
+                        Some((
+                            hir[sym].selection_range()?,
+                            TokenType::Variable,
+                            vec![TokenModifier::ReadOnly],
+                        ))
                     } else {
                         None
                     }
                 } else {
                     None
                 }
-            }),
-            SymbolKind::Binary(b) => {
-                b.op.as_ref()
-                    .and_then(BinaryOpKind::as_custom)
-                    .map(|c| (c.range, TokenType::CustomOperator, vec![]))
+            } else {
+                None
             }
-            _ => None,
+        }),
+        SymbolKind::Binary(b) => {
+            b.op.as_ref()
+                .and_then(BinaryOpKind::as_custom)
+                .map(|c| (c.range, TokenType::CustomOperator, vec![]))
         }
-    }));
+        _ => None,
+    }
+}
 
-    Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
-        result_id: None,
-        data: token_builder.finish(),
-    })))
+/// Computes the edits from the old tokens to the new ones
+/// as a single edit replacing everything between
+/// the common prefix and suffix.
+#[allow(clippy::cast_possible_truncation)]
+fn tokens_edits(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = old.len() - prefix - suffix;
+    let inserted = &new[prefix..new.len() - suffix];
+
+    if deleted == 0 && inserted.is_empty() {
+        return Vec::new();
+    }
+
+    // Each token is 5 integers in the encoded data.
+    vec![SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: (deleted * 5) as u32,
+        data: Some(inserted.to_vec()),
+    }]
 }
 
 fn token_for_ty(hir: &Hir, ty: Type) -> Option<TokenType> {
@@ -181,15 +365,6 @@ impl<'b> SemanticTokensBuilder<'b> {
             .push((range, ty, modifiers.into_iter().collect()));
     }
 
-    fn extend<M: IntoIterator<Item = TokenModifier>>(
-        &mut self,
-        iter: impl IntoIterator<Item = (TextRange, TokenType, M)>,
-    ) {
-        for (range, ty, modifiers) in iter {
-            self.add(range, ty, modifiers);
-        }
-    }
-
     fn finish(mut self) -> Vec<SemanticToken> {
         self.tokens.sort_by_key(|(range, ..)| range.start());
 
@@ -0,0 +1,67 @@
+use crate::{
+    lsp_ext::request::{
+        CustomOperatorStatus, DocumentStatus, HirStatus, ServerStatusResult, WorkspaceStatus,
+    },
+    world::World,
+};
+use lsp_async_stub::{rpc, Context, Params};
+use rhai_common::environment::Environment;
+use std::time::Duration;
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn server_status<E: Environment>(
+    context: Context<World<E>>,
+    _params: Params<()>,
+) -> Result<ServerStatusResult, rpc::Error> {
+    let workspaces = context.workspaces.snapshot();
+
+    let workspaces = workspaces
+        .iter()
+        .map(|(root, ws)| {
+            let mut custom_operators = ws
+                .custom_operators
+                .iter()
+                .map(
+                    |(name, lhs_ty, rhs_ty, binding_powers)| CustomOperatorStatus {
+                        name: name.clone(),
+                        lhs_type: lhs_ty.fmt(&ws.hir).to_string(),
+                        rhs_type: rhs_ty.fmt(&ws.hir).to_string(),
+                        binding_powers: *binding_powers,
+                    },
+                )
+                .collect::<Vec<_>>();
+            custom_operators.sort_by(|a, b| a.name.cmp(&b.name));
+
+            WorkspaceStatus {
+                root: root.clone(),
+                detached: ws.is_detached(),
+                documents: ws
+                    .documents
+                    .iter()
+                    .map(|(uri, doc)| DocumentStatus {
+                        uri: uri.clone(),
+                        is_def: doc.is_def,
+                        version: doc.version,
+                    })
+                    .collect(),
+                hir: HirStatus {
+                    sources: ws.hir.sources().count(),
+                    modules: ws.hir.modules().count(),
+                    scopes: ws.hir.scopes().count(),
+                    symbols: ws.hir.symbols().count(),
+                    types: ws.hir.types().count(),
+                },
+                missing_modules: ws.hir.missing_modules().collect(),
+                custom_operators,
+                last_parse_ms: ws.last_parse.map(millis),
+                last_resolve_ms: ws.last_resolve.map(millis),
+            }
+        })
+        .collect();
+
+    Ok(ServerStatusResult { workspaces })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -10,7 +10,7 @@ pub(crate) async fn syntax_tree<E: Environment>(
     params: Params<SyntaxTreeParams>,
 ) -> Result<Option<SyntaxTreeResult>, rpc::Error> {
     let p = params.required()?;
-    let workspaces = context.workspaces.read().await;
+    let workspaces = context.workspaces.snapshot();
     let ws = workspaces.by_document(&p.uri);
 
     let doc = ws.document(&p.uri)?;
@@ -6,7 +6,7 @@ use crate::{
     world::World,
 };
 
-use super::update_document;
+use super::{read_document, update_document};
 
 pub(crate) async fn watched_file_change<E: Environment>(
     context: Context<World<E>>,
@@ -20,40 +20,18 @@ pub(crate) async fn watched_file_change<E: Environment>(
     for change in params.changes {
         let uri = change.uri;
 
-        let mut workspaces = context.workspaces.write().await;
-
         match change.typ {
             FileChangeType::CREATED | FileChangeType::CHANGED => {
-                let path = match context.env.url_to_file_path(&uri) {
-                    Some(p) => p,
-                    None => {
-                        tracing::warn!(url = %uri, "could not create file path from url");
-                        continue;
-                    }
-                };
-
-                let file_content = match context.env.read_file(&path).await {
-                    Ok(c) => c,
-                    Err(err) => {
-                        tracing::error!(error = %err, "failed to read file");
-                        continue;
-                    }
-                };
-
-                let source = match String::from_utf8(file_content) {
-                    Ok(s) => s,
-                    Err(error) => {
-                        tracing::error!(url = %uri, %error, "source is not valid UTF-8");
-                        continue;
-                    }
-                };
-
-                drop(workspaces);
-                update_document(context.clone(), uri, &source).await;
+                if let Some(source) = read_document(&context.env, &uri).await {
+                    update_document(context.clone(), uri, &source, None).await;
+                }
             }
             FileChangeType::DELETED => {
+                let mut workspaces = context.workspaces.write().await;
                 let ws = workspaces.by_document_mut(&uri);
-                ws.remove_document(&uri) ;
+                ws.remove_document(&uri);
+                ws.resolve_all();
+                drop(workspaces);
                 clear_diagnostics(context.clone(), uri).await;
             }
             _ => {
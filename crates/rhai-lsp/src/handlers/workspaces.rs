@@ -1,9 +1,10 @@
 use super::update_configuration;
 use crate::{
+    progress::{percentage, Progress},
     world::{Workspace, World},
 };
 use lsp_async_stub::{Context, Params};
-use lsp_types::DidChangeWorkspaceFoldersParams;
+use lsp_types::{DidChangeWorkspaceFoldersParams, Url, WorkDoneProgressCancelParams};
 use rhai_common::environment::Environment;
 
 pub async fn workspace_change<E: Environment>(
@@ -21,17 +22,165 @@ pub async fn workspace_change<E: Environment>(
         workspaces.remove(&removed.uri);
     }
 
+    let mut added_urls = Vec::new();
+
     for added in p.event.added {
-        let mut ws = Workspace::new(context.env.clone(), added.uri.clone());
+        let mut ws = Workspace::new(
+            context.env.clone(),
+            added.uri.clone(),
+            **context.position_encoding.load(),
+        );
 
         if let Err(error) = ws.load_rhai_config().await {
             tracing::error!(%error, "invalid configuration");
         }
-        ws.load_all_files().await;
 
-        workspaces.entry(added.uri).or_insert(ws);
+        workspaces.add(ws);
+        added_urls.push(added.uri);
     }
 
     drop(workspaces);
-    update_configuration(context).await;
+    update_configuration(context.clone()).await;
+
+    for ws_url in added_urls {
+        load_workspace_files(context.clone(), ws_url).await;
+    }
+}
+
+pub(crate) async fn work_done_progress_cancel<E: Environment>(
+    context: Context<World<E>>,
+    params: Params<WorkDoneProgressCancelParams>,
+) {
+    if let Some(p) = params.optional() {
+        context.progress.cancel(&p.token);
+    }
+}
+
+/// The number of files that are added to a workspace at once while loading.
+const LOAD_BATCH_SIZE: usize = 64;
+
+/// Loads all files of a workspace while reporting the progress.
+///
+/// The files are added in batches, so that requests in the meantime
+/// are answered with the files loaded so far without copying
+/// the workspaces for every file.
+#[tracing::instrument(skip_all, fields(%ws_url))]
+pub(crate) async fn load_workspace_files<E: Environment>(context: Context<World<E>>, ws_url: Url) {
+    let mut progress = Progress::begin(context.clone(), "Loading Rhai files", true).await;
+
+    let message = load_files(&context, &ws_url, &mut progress)
+        .await
+        .unwrap_or_else(|| String::from("the workspace was removed"));
+
+    progress.end(message).await;
+}
+
+/// Loads the files and returns the message the progress ends with,
+/// or `None` if the workspace no longer exists.
+async fn load_files<E: Environment>(
+    context: &Context<World<E>>,
+    ws_url: &Url,
+    progress: &mut Progress<E>,
+) -> Option<String> {
+    let paths = context.workspaces.snapshot().get(ws_url)?.source_paths();
+
+    let total = paths.len();
+    progress
+        .report(format!("found {total} files"), percentage(0, total))
+        .await;
+
+    let mut parsed = 0;
+    let mut cancelled = false;
+    let mut batch = Vec::with_capacity(LOAD_BATCH_SIZE);
+
+    for (i, path) in paths.into_iter().enumerate() {
+        if progress.is_cancelled() {
+            cancelled = true;
+            break;
+        }
+
+        tracing::debug!(?path, "found file");
+
+        let document_url = Url::parse(&format!("file://{}", path.to_string_lossy())).unwrap();
+
+        match context.env.read_file(&path).await {
+            Ok(source) => match String::from_utf8(source) {
+                Ok(source_text) => batch.push((document_url, source_text)),
+                Err(error) => {
+                    tracing::error!(%error, "given source is not valid UTF-8");
+                }
+            },
+            Err(error) => {
+                tracing::error!(%error, "failed to read file");
+            }
+        }
+
+        if batch.len() < LOAD_BATCH_SIZE && i + 1 < total {
+            continue;
+        }
+
+        parsed += add_batch(context, ws_url, &mut batch).await?;
+
+        progress
+            .report(
+                format!("parsed {parsed}/{total} files"),
+                percentage(i + 1, total),
+            )
+            .await;
+    }
+
+    // Keep the files that were already read before cancelling.
+    parsed += add_batch(context, ws_url, &mut batch).await?;
+
+    progress
+        .report(format!("resolving {parsed} files"), None)
+        .await;
+
+    context
+        .workspaces
+        .write()
+        .await
+        .get_mut(ws_url)?
+        .resolve_all();
+
+    if cancelled {
+        tracing::warn!(
+            parsed,
+            total,
+            "loading files cancelled, the workspace is incomplete"
+        );
+        Some(format!(
+            "loading cancelled, only {parsed}/{total} files were loaded"
+        ))
+    } else {
+        Some(format!("loaded {parsed} files"))
+    }
+}
+
+/// Adds the files read so far to the workspace,
+/// returns `None` if the workspace no longer exists.
+async fn add_batch<E: Environment>(
+    context: &Context<World<E>>,
+    ws_url: &Url,
+    batch: &mut Vec<(Url, String)>,
+) -> Option<usize> {
+    if batch.is_empty() {
+        return Some(0);
+    }
+
+    let mut workspaces = context.workspaces.write().await;
+    let ws = workspaces.get_mut(ws_url)?;
+    let added = batch.len();
+
+    ws.parse_pass(|ws| {
+        for (document_url, source_text) in batch.drain(..) {
+            // The document might have been opened by the client in the meantime,
+            // in which case its contents are newer than the ones on disk.
+            if !ws.is_outdated_change(&document_url, None) {
+                ws.add_document(document_url, &source_text, None);
+            }
+        }
+    });
+
+    Some(added)
 }
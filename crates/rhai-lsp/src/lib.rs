@@ -20,6 +20,10 @@ pub use world::{World, WorldState};
 pub(crate) mod config;
 pub(crate) mod diagnostics;
 pub(crate) mod lsp_ext;
+pub(crate) mod mapper;
+pub(crate) mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod run;
 pub(crate) mod utils;
 pub(crate) mod world;
 
@@ -30,17 +34,28 @@ pub(crate) type IndexMap<K, V> = indexmap::IndexMap<K, V, ahash::RandomState>;
 #[must_use]
 pub fn create_server<E: Environment>() -> Server<World<E>> {
     Server::new()
-        .on_request::<request::Initialize, _>(handlers::initialize)
+        .on_request::<lsp_ext::request::Initialize, _>(handlers::initialize)
         .on_request::<request::FoldingRangeRequest, _>(handlers::folding_ranges)
+        .on_request::<request::SelectionRangeRequest, _>(handlers::selection_range)
         .on_request::<request::GotoDeclaration, _>(handlers::goto_declaration)
         .on_request::<request::GotoDefinition, _>(handlers::goto_definition)
-        .on_request::<request::References, _>(handlers::references)
+        .on_request::<request::GotoTypeDefinition, _>(handlers::goto_type_definition)
+        .on_request::<lsp_ext::request::References, _>(handlers::references)
+        .on_request::<request::DocumentHighlightRequest, _>(handlers::document_highlight)
         .on_request::<request::DocumentSymbolRequest, _>(handlers::document_symbols)
+        .on_request::<request::DocumentLinkRequest, _>(handlers::document_links)
         .on_request::<request::HoverRequest, _>(handlers::hover)
         .on_request::<request::SemanticTokensFullRequest, _>(handlers::semantic_tokens)
+        .on_request::<request::SemanticTokensFullDeltaRequest, _>(handlers::semantic_tokens_delta)
+        .on_request::<request::SemanticTokensRangeRequest, _>(handlers::semantic_tokens_range)
+        .on_request::<request::CodeLensRequest, _>(handlers::code_lens)
+        .on_request::<request::CodeLensResolve, _>(handlers::code_lens_resolve)
+        .on_request::<request::ExecuteCommand, _>(handlers::execute_command)
         .on_request::<request::Completion, _>(handlers::completion)
+        .on_request::<request::ResolveCompletionItem, _>(handlers::completion_resolve)
         .on_request::<request::PrepareRenameRequest, _>(handlers::prepare_rename)
         .on_request::<request::Rename, _>(handlers::rename)
+        .on_request::<request::WillRenameFiles, _>(handlers::will_rename_files)
         .on_notification::<notification::Initialized, _>(handlers::initialized)
         .on_notification::<notification::DidOpenTextDocument, _>(handlers::document_open)
         .on_notification::<notification::DidChangeTextDocument, _>(handlers::document_change)
@@ -48,9 +63,14 @@ pub fn create_server<E: Environment>() -> Server<World<E>> {
         .on_notification::<notification::DidCloseTextDocument, _>(handlers::document_close)
         .on_notification::<notification::DidChangeConfiguration, _>(handlers::configuration_change)
         .on_notification::<notification::DidChangeWorkspaceFolders, _>(handlers::workspace_change)
+        .on_notification::<notification::WorkDoneProgressCancel, _>(handlers::work_done_progress_cancel)
         .on_notification::<notification::DidChangeWatchedFiles, _>(handlers::watched_file_change)
+        .on_notification::<notification::DidRenameFiles, _>(handlers::did_rename_files)
         .on_request::<lsp_ext::request::SyntaxTree, _>(handlers::syntax_tree)
         .on_request::<lsp_ext::request::ConvertOffsets, _>(handlers::convert_offsets)
+        .on_request::<lsp_ext::request::ServerStatus, _>(handlers::server_status)
+        .on_request::<lsp_ext::request::DocumentDiagnostic, _>(handlers::document_diagnostic)
+        .on_request::<lsp_ext::request::WorkspaceDiagnostic, _>(handlers::workspace_diagnostic)
         .build()
 }
 
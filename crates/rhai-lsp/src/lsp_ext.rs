@@ -2,6 +2,40 @@ pub mod request {
     use lsp_types::{request::Request, Url};
     use serde::{Deserialize, Serialize};
 
+    /// `initialize` that also keeps the raw client capabilities,
+    /// as some of them are not part of `lsp_types` yet.
+    pub enum Initialize {}
+
+    pub struct InitializeParams {
+        pub params: lsp_types::InitializeParams,
+        pub capabilities: serde_json::Value,
+    }
+
+    impl Serialize for InitializeParams {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.params.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for InitializeParams {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let value = serde_json::Value::deserialize(deserializer)?;
+
+            Ok(Self {
+                capabilities: value.get("capabilities").cloned().unwrap_or_default(),
+                params: serde_json::from_value(value).map_err(serde::de::Error::custom)?,
+            })
+        }
+    }
+
+    impl Request for Initialize {
+        type Params = InitializeParams;
+
+        type Result = lsp_types::InitializeResult;
+
+        const METHOD: &'static str = "initialize";
+    }
+
     pub enum SyntaxTree {}
 
     #[derive(Serialize, Deserialize)]
@@ -36,9 +70,9 @@ pub mod request {
 
     #[derive(Serialize, Deserialize)]
     pub struct ConvertOffsetsResult {
-        /// LSP-compatible UTF-16 ranges.
+        /// LSP ranges in the negotiated position encoding.
         pub ranges: Option<Vec<lsp_types::Range>>,
-        /// LSP-compatible UTF-16 positions.
+        /// LSP positions in the negotiated position encoding.
         pub positions: Option<Vec<lsp_types::Position>>,
     }
 
@@ -49,4 +83,184 @@ pub mod request {
 
         const METHOD: &'static str = "rhai/convertOffsets";
     }
+
+    pub enum ServerStatus {}
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ServerStatusResult {
+        pub workspaces: Vec<WorkspaceStatus>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct WorkspaceStatus {
+        pub root: Url,
+        /// Whether this is the workspace for documents outside of any workspace folder.
+        pub detached: bool,
+        pub documents: Vec<DocumentStatus>,
+        pub hir: HirStatus,
+        pub missing_modules: Vec<Url>,
+        pub custom_operators: Vec<CustomOperatorStatus>,
+        /// How long the last parse pass took, in milliseconds.
+        pub last_parse_ms: Option<f64>,
+        /// How long the last resolve pass took, in milliseconds.
+        pub last_resolve_ms: Option<f64>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DocumentStatus {
+        pub uri: Url,
+        pub is_def: bool,
+        pub version: Option<i32>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct HirStatus {
+        pub sources: usize,
+        pub modules: usize,
+        pub scopes: usize,
+        pub symbols: usize,
+        pub types: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CustomOperatorStatus {
+        pub name: String,
+        pub lhs_type: String,
+        pub rhs_type: String,
+        pub binding_powers: (u8, u8),
+    }
+
+    impl Request for ServerStatus {
+        type Params = ();
+
+        type Result = ServerStatusResult;
+
+        const METHOD: &'static str = "rhai/serverStatus";
+    }
+
+    /// `textDocument/references` with an optional filter
+    /// for references that only read or write the symbol.
+    pub enum References {}
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ReferencesParams {
+        #[serde(flatten)]
+        pub params: lsp_types::ReferenceParams,
+        pub access: Option<ReferenceAccess>,
+    }
+
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ReferenceAccess {
+        Read,
+        Write,
+    }
+
+    impl Request for References {
+        type Params = ReferencesParams;
+
+        type Result = Option<Vec<lsp_types::Location>>;
+
+        const METHOD: &'static str = "textDocument/references";
+    }
+
+    // Pull diagnostics from LSP 3.17, these are not part of `lsp_types` yet.
+
+    pub enum DocumentDiagnostic {}
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DocumentDiagnosticParams {
+        pub text_document: lsp_types::TextDocumentIdentifier,
+        pub identifier: Option<String>,
+        pub previous_result_id: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "kind", rename_all = "lowercase")]
+    pub enum DocumentDiagnosticReport {
+        Full(FullDocumentDiagnosticReport),
+        Unchanged(UnchangedDocumentDiagnosticReport),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FullDocumentDiagnosticReport {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub result_id: Option<String>,
+        pub items: Vec<lsp_types::Diagnostic>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct UnchangedDocumentDiagnosticReport {
+        pub result_id: String,
+    }
+
+    impl Request for DocumentDiagnostic {
+        type Params = DocumentDiagnosticParams;
+
+        type Result = DocumentDiagnosticReport;
+
+        const METHOD: &'static str = "textDocument/diagnostic";
+    }
+
+    pub enum WorkspaceDiagnostic {}
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct WorkspaceDiagnosticParams {
+        pub identifier: Option<String>,
+        pub previous_result_ids: Vec<PreviousResultId>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct PreviousResultId {
+        pub uri: Url,
+        pub value: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct WorkspaceDiagnosticReport {
+        pub items: Vec<WorkspaceDocumentDiagnosticReport>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct WorkspaceDocumentDiagnosticReport {
+        pub uri: Url,
+        pub version: Option<i32>,
+        #[serde(flatten)]
+        pub report: DocumentDiagnosticReport,
+    }
+
+    impl Request for WorkspaceDiagnostic {
+        type Params = WorkspaceDiagnosticParams;
+
+        type Result = WorkspaceDiagnosticReport;
+
+        const METHOD: &'static str = "workspace/diagnostic";
+    }
+
+    pub enum WorkspaceDiagnosticRefresh {}
+
+    impl Request for WorkspaceDiagnosticRefresh {
+        type Params = ();
+
+        type Result = ();
+
+        const METHOD: &'static str = "workspace/diagnostic/refresh";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DiagnosticRegistrationOptions {
+        pub document_selector: Option<lsp_types::DocumentSelector>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub identifier: Option<String>,
+        pub inter_file_dependencies: bool,
+        pub workspace_diagnostics: bool,
+    }
 }
@@ -0,0 +1,272 @@
+//! Mapping between byte offsets and LSP positions
+//! in the position encoding negotiated with the client.
+
+use lsp_async_stub::util::{Position, Range};
+use lsp_types::PositionEncodingKind;
+use rhai_rowan::{TextRange, TextSize};
+use std::sync::Arc;
+
+/// How the characters of a line are counted in positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PositionEncoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl Default for PositionEncoding {
+    /// UTF-16 is the only encoding every client supports.
+    fn default() -> Self {
+        Self::Utf16
+    }
+}
+
+impl PositionEncoding {
+    /// Picks the first encoding in the order of the client's
+    /// preference that we support.
+    pub(crate) fn negotiate(client_encodings: Option<&[PositionEncodingKind]>) -> Self {
+        client_encodings
+            .into_iter()
+            .flatten()
+            .find_map(|kind| match kind.as_str() {
+                "utf-8" => Some(Self::Utf8),
+                "utf-16" => Some(Self::Utf16),
+                "utf-32" => Some(Self::Utf32),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+            Self::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    fn len(self, c: char) -> u64 {
+        match self {
+            Self::Utf8 => c.len_utf8() as u64,
+            Self::Utf16 => c.len_utf16() as u64,
+            Self::Utf32 => 1,
+        }
+    }
+}
+
+/// Translates byte offsets in a text to zero-based positions and back.
+#[derive(Debug, Clone)]
+pub struct Mapper {
+    encoding: PositionEncoding,
+    text: Arc<str>,
+    /// The offset of the first character of each line.
+    line_starts: Vec<usize>,
+}
+
+impl Mapper {
+    pub(crate) fn new(text: &str, encoding: PositionEncoding) -> Self {
+        let line_starts = [0]
+            .into_iter()
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        Self {
+            encoding,
+            text: text.into(),
+            line_starts,
+        }
+    }
+
+    /// The offset at the given position, positions inside a character
+    /// or beyond the end of the line have no offset.
+    ///
+    /// Line breaks are `\n` or `\r\n`, and are not part of the line.
+    #[must_use]
+    pub fn offset(&self, position: Position) -> Option<TextSize> {
+        let line_start = *self.line_starts.get(usize::try_from(position.line).ok()?)?;
+        let line = self.line_text(line_start);
+
+        let mut character = 0;
+
+        for (idx, c) in line.char_indices() {
+            if character == position.character {
+                return TextSize::try_from(line_start + idx).ok();
+            }
+
+            character += self.encoding.len(c);
+
+            if character > position.character {
+                return None;
+            }
+        }
+
+        if character == position.character {
+            return TextSize::try_from(line_start + line.len()).ok();
+        }
+
+        None
+    }
+
+    #[must_use]
+    pub fn text_range(&self, range: Range) -> Option<TextRange> {
+        self.offset(range.start)
+            .and_then(|start| self.offset(range.end).map(|end| TextRange::new(start, end)))
+    }
+
+    /// The position of the character at the given offset,
+    /// offsets inside a character are at the start of the character,
+    /// and offsets inside a line break are at the end of the line.
+    #[must_use]
+    pub fn position(&self, offset: TextSize) -> Option<Position> {
+        let offset = usize::from(offset);
+
+        if offset > self.text.len() {
+            return None;
+        }
+
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+
+        let character = self
+            .line_text(line_start)
+            .char_indices()
+            .take_while(|(idx, c)| line_start + idx + c.len_utf8() <= offset)
+            .map(|(_, c)| self.encoding.len(c))
+            .sum();
+
+        Some(Position {
+            line: line as u64,
+            character,
+        })
+    }
+
+    #[must_use]
+    pub fn range(&self, range: TextRange) -> Option<Range> {
+        self.position(range.start())
+            .and_then(|start| self.position(range.end()).map(|end| Range { start, end }))
+    }
+
+    /// The text of the line that starts at the given offset, without the line break.
+    fn line_text(&self, line_start: usize) -> &str {
+        let line = self.text[line_start..]
+            .find('\n')
+            .map_or(&self.text[line_start..], |idx| {
+                &self.text[line_start..line_start + idx]
+            });
+
+        line.strip_suffix('\r').unwrap_or(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u64, character: u64) -> Position {
+        Position { line, character }
+    }
+
+    fn ofs(offset: u32) -> TextSize {
+        TextSize::from(offset)
+    }
+
+    #[test]
+    fn test_multi_byte_characters() {
+        // `é` is 2 bytes in UTF-8 and 1 code unit in UTF-16,
+        // `𝄞` is 4 bytes in UTF-8 and 2 code units in UTF-16.
+        let text = "é𝄞x";
+
+        let expected = [
+            (PositionEncoding::Utf8, [0, 2, 6]),
+            (PositionEncoding::Utf16, [0, 1, 3]),
+            (PositionEncoding::Utf32, [0, 1, 2]),
+        ];
+
+        for (encoding, characters) in expected {
+            let mapper = Mapper::new(text, encoding);
+
+            for (offset, character) in [0, 2, 6].into_iter().zip(characters) {
+                assert_eq!(mapper.position(ofs(offset)), Some(pos(0, character)));
+                assert_eq!(mapper.offset(pos(0, character)), Some(ofs(offset)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_positions_inside_a_character() {
+        let mapper = Mapper::new("𝄞x", PositionEncoding::Utf16);
+        assert_eq!(mapper.offset(pos(0, 1)), None);
+
+        let mapper = Mapper::new("𝄞x", PositionEncoding::Utf8);
+        assert_eq!(mapper.offset(pos(0, 2)), None);
+    }
+
+    #[test]
+    fn test_offsets_inside_a_character() {
+        let mapper = Mapper::new("a𝄞x", PositionEncoding::Utf16);
+
+        for offset in 2..5 {
+            assert_eq!(mapper.position(ofs(offset)), Some(pos(0, 1)));
+        }
+
+        assert_eq!(mapper.position(ofs(5)), Some(pos(0, 3)));
+    }
+
+    #[test]
+    fn test_crlf() {
+        let mapper = Mapper::new("ab\r\nc\r\n", PositionEncoding::Utf16);
+
+        assert_eq!(mapper.offset(pos(0, 2)), Some(ofs(2)));
+        assert_eq!(mapper.offset(pos(0, 3)), None);
+        assert_eq!(mapper.offset(pos(1, 0)), Some(ofs(4)));
+        assert_eq!(mapper.offset(pos(1, 1)), Some(ofs(5)));
+        assert_eq!(mapper.offset(pos(2, 0)), Some(ofs(7)));
+
+        assert_eq!(mapper.position(ofs(2)), Some(pos(0, 2)));
+        assert_eq!(mapper.position(ofs(3)), Some(pos(0, 2)));
+        assert_eq!(mapper.position(ofs(4)), Some(pos(1, 0)));
+        assert_eq!(mapper.position(ofs(7)), Some(pos(2, 0)));
+    }
+
+    #[test]
+    fn test_end_of_line() {
+        let mapper = Mapper::new("ab\nc", PositionEncoding::Utf16);
+
+        assert_eq!(mapper.offset(pos(0, 2)), Some(ofs(2)));
+        assert_eq!(mapper.offset(pos(0, 3)), None);
+        assert_eq!(mapper.position(ofs(2)), Some(pos(0, 2)));
+        assert_eq!(mapper.position(ofs(3)), Some(pos(1, 0)));
+    }
+
+    #[test]
+    fn test_end_of_file() {
+        let mapper = Mapper::new("ab\nc", PositionEncoding::Utf16);
+
+        assert_eq!(mapper.offset(pos(1, 1)), Some(ofs(4)));
+        assert_eq!(mapper.offset(pos(1, 2)), None);
+        assert_eq!(mapper.offset(pos(2, 0)), None);
+        assert_eq!(mapper.position(ofs(4)), Some(pos(1, 1)));
+        assert_eq!(mapper.position(ofs(5)), None);
+
+        let mapper = Mapper::new("ab\n", PositionEncoding::Utf16);
+
+        assert_eq!(mapper.offset(pos(1, 0)), Some(ofs(3)));
+        assert_eq!(mapper.position(ofs(3)), Some(pos(1, 0)));
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let utf8 = PositionEncodingKind::UTF8;
+        let utf32 = PositionEncodingKind::UTF32;
+
+        assert_eq!(PositionEncoding::negotiate(None), PositionEncoding::Utf16);
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&[utf32, utf8])),
+            PositionEncoding::Utf32
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&["utf-7".into()])),
+            PositionEncoding::Utf16
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Work done progress reporting for long running jobs.
+
+use crate::world::World;
+use anyhow::Context as AnyhowContext;
+use lsp_async_stub::{Context, RequestWriter};
+use lsp_types::{
+    notification, request, NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress,
+    WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+    WorkDoneProgressReport,
+};
+use rhai_common::environment::Environment;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Progress jobs that are currently running.
+#[derive(Default)]
+pub(crate) struct ProgressJobs {
+    next_id: AtomicU64,
+    cancelled: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ProgressJobs {
+    /// Cancels the job with the given token, if it is still running.
+    pub(crate) fn cancel(&self, token: &NumberOrString) {
+        let token = match token {
+            NumberOrString::String(s) => s.clone(),
+            NumberOrString::Number(n) => n.to_string(),
+        };
+
+        if let Some(cancelled) = self.cancelled.lock().unwrap().get(&token) {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// A running job that reports its progress to the client.
+///
+/// If the client does not support work done progress,
+/// nothing is reported and the job cannot be cancelled.
+pub(crate) struct Progress<E: Environment> {
+    context: Context<World<E>>,
+    token: Option<String>,
+    cancelled: Arc<AtomicBool>,
+    percentage: Option<u32>,
+}
+
+impl<E: Environment> Progress<E> {
+    pub(crate) async fn begin(
+        mut context: Context<World<E>>,
+        title: &str,
+        cancellable: bool,
+    ) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let supported = context
+            .client_capabilities
+            .load()
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+
+        if !supported {
+            return Self {
+                context,
+                token: None,
+                cancelled,
+                percentage: None,
+            };
+        }
+
+        let token = format!(
+            "rhai-lsp/{}",
+            context.progress.next_id.fetch_add(1, Ordering::SeqCst)
+        );
+
+        let created = context
+            .write_request::<request::WorkDoneProgressCreate, _>(Some(
+                WorkDoneProgressCreateParams {
+                    token: NumberOrString::String(token.clone()),
+                },
+            ))
+            .await
+            .context("failed to create progress")
+            .and_then(|res| res.into_result().context("invalid progress response"));
+
+        if let Err(error) = created {
+            tracing::debug!(?error, "progress is not reported");
+            return Self {
+                context,
+                token: None,
+                cancelled,
+                percentage: None,
+            };
+        }
+
+        context
+            .progress
+            .cancelled
+            .lock()
+            .unwrap()
+            .insert(token.clone(), cancelled.clone());
+
+        let mut progress = Self {
+            context,
+            token: Some(token),
+            cancelled,
+            percentage: None,
+        };
+
+        progress
+            .send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.into(),
+                cancellable: Some(cancellable),
+                message: None,
+                percentage: Some(0),
+            }))
+            .await;
+
+        progress
+    }
+
+    /// Whether the client has cancelled the job.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Reports the progress, the message is only sent
+    /// if the percentage changes to avoid flooding the client.
+    pub(crate) async fn report(&mut self, message: String, percentage: Option<u32>) {
+        if percentage.is_some() && percentage == self.percentage {
+            return;
+        }
+
+        self.percentage = percentage.or(self.percentage);
+
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: None,
+            message: Some(message),
+            percentage: self.percentage,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn end(mut self, message: String) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(message),
+        }))
+        .await;
+
+        if let Some(token) = &self.token {
+            self.context
+                .progress
+                .cancelled
+                .lock()
+                .unwrap()
+                .remove(token);
+        }
+    }
+
+    async fn send(&mut self, progress: WorkDoneProgress) {
+        let token = match &self.token {
+            Some(token) => token.clone(),
+            None => return,
+        };
+
+        self.context
+            .write_notification::<notification::Progress, _>(Some(ProgressParams {
+                token: NumberOrString::String(token),
+                value: ProgressParamsValue::WorkDone(progress),
+            }))
+            .await
+            .unwrap_or_else(|err| tracing::error!("{err}"));
+    }
+}
+
+/// The percentage of the work that is done.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn percentage(done: usize, total: usize) -> Option<u32> {
+    if total == 0 {
+        return None;
+    }
+
+    Some((done * 100 / total) as u32)
+}
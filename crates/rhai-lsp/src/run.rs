@@ -0,0 +1,166 @@
+//! Running scripts in a sandboxed engine.
+
+use rhai::{Engine, EvalAltResult, Position};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// The maximum amount of operations a script can execute.
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// The maximum depth of nested function calls.
+const MAX_CALL_LEVELS: usize = 64;
+
+/// The maximum depth of expressions at the global and function levels.
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+/// The maximum length of strings in bytes.
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+/// The maximum number of items in arrays.
+const MAX_ARRAY_SIZE: usize = 100_000;
+
+/// The maximum number of properties in object maps.
+const MAX_MAP_SIZE: usize = 100_000;
+
+/// The maximum amount of time a script can run for.
+const TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// The maximum number of `print` and `debug` outputs sent to the client,
+/// the rest of the output is dropped.
+const MAX_OUTPUT_LINES: usize = 1000;
+
+#[derive(Debug)]
+pub(crate) enum RunEvent {
+    /// Output from `print` or `debug`.
+    Output(String),
+    /// The script has finished, this is always the last event.
+    Finished(Result<String, RunError>),
+}
+
+#[derive(Debug)]
+pub(crate) struct RunError {
+    pub(crate) message: String,
+    /// The 1-based line and column (in characters) of the error, if known.
+    pub(crate) position: Option<(usize, usize)>,
+}
+
+/// Runs the given script on a separate thread.
+///
+/// Modules are resolved relative to `base_dir` if given.
+pub(crate) fn run_script(script: String, base_dir: Option<PathBuf>) -> UnboundedReceiver<RunEvent> {
+    let (tx, rx) = unbounded_channel();
+
+    thread::spawn(move || {
+        let engine = create_engine(&tx, base_dir);
+
+        let result = match engine.eval::<rhai::Dynamic>(&script) {
+            Ok(value) if value.is_unit() => Ok(String::new()),
+            Ok(value) => Ok(value.to_string()),
+            Err(err) => Err(run_error(*err)),
+        };
+
+        tx.send(RunEvent::Finished(result)).ok();
+    });
+
+    rx
+}
+
+fn create_engine(tx: &UnboundedSender<RunEvent>, base_dir: Option<PathBuf>) -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_map_size(MAX_MAP_SIZE)
+        .disable_symbol("eval");
+
+    if let Some(base_dir) = base_dir {
+        engine.set_module_resolver(rhai::module_resolvers::FileModuleResolver::new_with_path(
+            base_dir,
+        ));
+    }
+
+    let start = Instant::now();
+    engine.on_progress(move |_| {
+        if start.elapsed() > TIME_LIMIT {
+            Some(rhai::Dynamic::UNIT)
+        } else {
+            None
+        }
+    });
+
+    let output = OutputSender {
+        tx: tx.clone(),
+        lines: Arc::new(AtomicUsize::new(0)),
+    };
+
+    let print_output = output.clone();
+    engine.on_print(move |s| print_output.send(s.to_string()));
+
+    engine.on_debug(move |s, _, pos| {
+        output.send(match pos.line() {
+            Some(line) => format!("[{line}] {s}"),
+            None => s.to_string(),
+        });
+    });
+
+    engine
+}
+
+/// Sends the output of a script until [`MAX_OUTPUT_LINES`] is reached,
+/// so that scripts printing in a loop do not flood the client.
+#[derive(Clone)]
+struct OutputSender {
+    tx: UnboundedSender<RunEvent>,
+    lines: Arc<AtomicUsize>,
+}
+
+impl OutputSender {
+    fn send(&self, output: String) {
+        let lines = self.lines.fetch_add(1, Ordering::Relaxed);
+
+        let output = match lines.cmp(&MAX_OUTPUT_LINES) {
+            std::cmp::Ordering::Less => output,
+            std::cmp::Ordering::Equal => {
+                format!("output truncated after {MAX_OUTPUT_LINES} lines")
+            }
+            std::cmp::Ordering::Greater => return,
+        };
+
+        self.tx.send(RunEvent::Output(output)).ok();
+    }
+}
+
+fn run_error(mut err: EvalAltResult) -> RunError {
+    let position = err.take_position();
+
+    let message = match err {
+        EvalAltResult::ErrorTerminated(..) => {
+            format!(
+                "script exceeded the time limit of {}s",
+                TIME_LIMIT.as_secs()
+            )
+        }
+        err => err.to_string(),
+    };
+
+    RunError {
+        message,
+        position: position_of(position),
+    }
+}
+
+fn position_of(position: Position) -> Option<(usize, usize)> {
+    Some((position.line()?, position.position().unwrap_or(1)))
+}
@@ -5,28 +5,31 @@ use futures::{
     future::{AbortHandle, Abortable},
     Future,
 };
-use rhai_hir::{symbol::SymbolKind, Hir, Symbol};
+use lsp_async_stub::{rpc, Context};
+use rhai_hir::{
+    symbol::{DeclSymbol, ReferenceSymbol, ReferenceTarget, SymbolKind},
+    ty::Type,
+    Hir, Symbol,
+};
 
 use rhai_common::environment::Environment;
+use url::Url;
+
+use crate::world::World;
 
 /// Format signatures and definitions of symbols.
 pub fn signature_of(hir: &Hir, symbol: Symbol) -> String {
     let sym_data = &hir[symbol];
 
     match &sym_data.kind {
-        SymbolKind::Decl(decl) => {
-            format!(
-                "{}{}: {}",
-                if decl.is_param {
-                    ""
-                } else if decl.is_const {
-                    "const "
-                } else {
-                    "let "
-                },
-                decl.name,
-                sym_data.ty.fmt(hir)
-            )
+        SymbolKind::Decl(decl) => decl_signature(hir, decl, sym_data.ty),
+        // References to variables can have a narrower type
+        // than the declaration.
+        SymbolKind::Reference(ReferenceSymbol {
+            target: Some(ReferenceTarget::Symbol(target)),
+            ..
+        }) if hir[*target].kind.is_decl() => {
+            decl_signature(hir, hir[*target].kind.as_decl().unwrap(), sym_data.ty)
         }
         _ => {
             format!("{}", sym_data.ty.fmt(hir))
@@ -34,6 +37,21 @@ pub fn signature_of(hir: &Hir, symbol: Symbol) -> String {
     }
 }
 
+fn decl_signature(hir: &Hir, decl: &DeclSymbol, ty: Type) -> String {
+    format!(
+        "{}{}: {}",
+        if decl.is_param {
+            ""
+        } else if decl.is_const {
+            "const "
+        } else {
+            "let "
+        },
+        decl.name,
+        ty.fmt(hir)
+    )
+}
+
 pub fn documentation_for(hir: &Hir, symbol: Symbol, signature: bool) -> String {
     if let Some(m) = hir.target_module(symbol) {
         return hir[m].docs.clone();
@@ -45,7 +63,13 @@ pub fn documentation_for(hir: &Hir, symbol: Symbol, signature: bool) -> String {
         String::new()
     };
 
-    let sym_data = &hir[symbol];
+    let sym_data = match &hir[symbol].kind {
+        SymbolKind::Reference(ReferenceSymbol {
+            target: Some(ReferenceTarget::Symbol(target)),
+            ..
+        }) => &hir[*target],
+        _ => &hir[symbol],
+    };
 
     if let Some(docs) = sym_data.docs() {
         return format!(
@@ -62,6 +86,72 @@ pub fn documentation_for(hir: &Hir, symbol: Symbol, signature: bool) -> String {
     String::new()
 }
 
+/// The path used to import the module with the given URL.
+pub fn import_path_of(document_url: &Url, module_url: &Url) -> Option<String> {
+    if module_url.scheme() != "file" {
+        return Some(module_url.to_string());
+    }
+
+    match document_url.make_relative(module_url)? {
+        path if path.starts_with("../") => Some(path),
+        path => Some(format!("./{path}")),
+    }
+}
+
+/// Fails a request that was cancelled by the client,
+/// or that would be answered from a stale document
+/// because newer changes are not applied yet.
+pub(crate) fn check_request<E: Environment>(
+    context: &mut Context<World<E>>,
+    document_url: &Url,
+) -> Result<(), rpc::Error> {
+    if context.cancel_token().is_cancelled() {
+        return Err(rpc::Error::request_cancelled());
+    }
+
+    if context.pending_changes.contains(document_url) {
+        return Err(rpc::Error::content_modified());
+    }
+
+    Ok(())
+}
+
+/// How often long running requests are checked again.
+const REQUEST_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Checks a long running request again while it is answered,
+/// see [`check_request`].
+pub(crate) struct RequestCheckpoint {
+    last_check: Duration,
+}
+
+impl RequestCheckpoint {
+    pub(crate) fn new<E: Environment>(context: &Context<World<E>>) -> Self {
+        Self {
+            last_check: context.env.now(),
+        }
+    }
+
+    /// Yields to other tasks so that cancellations and changes
+    /// can be received, and checks the request again.
+    ///
+    /// This does nothing until some time has passed since the last check.
+    pub(crate) async fn check<E: Environment>(
+        &mut self,
+        context: &mut Context<World<E>>,
+        document_url: &Url,
+    ) -> Result<(), rpc::Error> {
+        if context.env.now().saturating_sub(self.last_check) < REQUEST_CHECK_INTERVAL {
+            return Ok(());
+        }
+
+        context.env.sleep(Duration::ZERO).await;
+        self.last_check = context.env.now();
+
+        check_request(context, document_url)
+    }
+}
+
 pub trait RhaiStringExt {
     fn wrap_rhai_markdown(&self) -> String;
 }
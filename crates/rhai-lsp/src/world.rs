@@ -1,12 +1,14 @@
 use crate::{
     config::{InitConfig, LspConfig},
+    mapper::{Mapper, PositionEncoding},
+    progress::ProgressJobs,
     utils::Debouncer,
     IndexMap,
 };
 use anyhow::anyhow;
 use arc_swap::ArcSwap;
-use lsp_async_stub::{rpc, util::Mapper};
-use lsp_types::Url;
+use lsp_async_stub::rpc;
+use lsp_types::{ClientCapabilities, SemanticToken, Url};
 use once_cell::sync::Lazy;
 use rhai_common::{config::Config, environment::Environment, util::Normalize};
 use rhai_hir::{ty::Type, Hir};
@@ -14,8 +16,13 @@ use rhai_rowan::{
     parser::{Operator, Parse, Parser},
     util::{is_rhai_def, is_valid_ident},
 };
-use std::{collections::HashSet, sync::Arc, time::Duration};
-use tokio::sync::RwLock as AsyncRwLock;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard, Notify};
 
 pub static DEFAULT_WORKSPACE_URL: Lazy<Url> = Lazy::new(|| Url::parse("root:///").unwrap());
 
@@ -23,30 +30,166 @@ pub type World<E> = Arc<WorldState<E>>;
 
 pub struct WorldState<E: Environment> {
     pub(crate) init_config: ArcSwap<InitConfig>,
+    pub(crate) client_capabilities: ArcSwap<ClientCapabilities>,
+    /// The client capabilities as they were sent,
+    /// including the ones missing from [`ClientCapabilities`].
+    pub(crate) raw_client_capabilities: ArcSwap<serde_json::Value>,
+    /// The position encoding negotiated with the client.
+    pub(crate) position_encoding: ArcSwap<PositionEncoding>,
     pub(crate) env: E,
-    pub(crate) workspaces: AsyncRwLock<Workspaces<E>>,
+    pub(crate) workspaces: SharedWorkspaces<E>,
+    pub(crate) pending_changes: PendingChanges,
     pub(crate) all_diagnostics_debouncer: Debouncer<E>,
+    pub(crate) progress: ProgressJobs,
+    /// Whether the client pulls diagnostics,
+    /// in which case they are no longer published.
+    pub(crate) pull_diagnostics: AtomicBool,
+    /// Notified when diagnostics might have changed,
+    /// pending workspace diagnostic requests wait for it.
+    pub(crate) diagnostics_changed: Notify,
 }
 
 impl<E: Environment> WorldState<E> {
     pub fn new(env: E) -> Self {
         let mut ws = Workspaces(IndexMap::default());
 
-        ws.insert(
+        ws.add(Workspace::new(
+            env.clone(),
             DEFAULT_WORKSPACE_URL.clone(),
-            Workspace::new(env.clone(), DEFAULT_WORKSPACE_URL.clone()),
-        );
+            PositionEncoding::default(),
+        ));
 
         Self {
             init_config: Default::default(),
+            client_capabilities: Default::default(),
+            raw_client_capabilities: Default::default(),
+            position_encoding: Default::default(),
             all_diagnostics_debouncer: Debouncer::new(Duration::from_secs(1), env.clone()),
             env,
-            workspaces: AsyncRwLock::new(ws),
+            workspaces: SharedWorkspaces::new(ws),
+            progress: Default::default(),
+            pending_changes: Default::default(),
+            pull_diagnostics: Default::default(),
+            diagnostics_changed: Default::default(),
+        }
+    }
+
+    /// Whether the client supports the capability
+    /// at the given JSON pointer in its capabilities.
+    pub(crate) fn client_supports(&self, capability: &str) -> bool {
+        self.raw_client_capabilities
+            .load()
+            .pointer(capability)
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false)
+    }
+}
+
+/// Workspaces shared between the handlers.
+///
+/// The workspaces are owned by writers, and requests are answered
+/// from immutable snapshots of them. Readers never wait for writers,
+/// and the changes are only visible in new snapshots after
+/// the write guard is dropped.
+pub struct SharedWorkspaces<E: Environment> {
+    snapshot: ArcSwap<WorkspacesSnapshot<E>>,
+    workspaces: AsyncMutex<Workspaces<E>>,
+}
+
+impl<E: Environment> SharedWorkspaces<E> {
+    fn new(mut workspaces: Workspaces<E>) -> Self {
+        Self {
+            snapshot: ArcSwap::from_pointee(workspaces.snapshot()),
+            workspaces: AsyncMutex::new(workspaces),
+        }
+    }
+
+    /// The last state of the workspaces that was written.
+    pub fn snapshot(&self) -> Arc<WorkspacesSnapshot<E>> {
+        self.snapshot.load_full()
+    }
+
+    /// Waits for other writers to finish.
+    pub async fn write(&self) -> WorkspacesWriteGuard<'_, E> {
+        WorkspacesWriteGuard {
+            shared: self,
+            workspaces: self.workspaces.lock().await,
+            modified: false,
         }
     }
 }
 
-#[repr(transparent)]
+pub struct WorkspacesWriteGuard<'s, E: Environment> {
+    shared: &'s SharedWorkspaces<E>,
+    workspaces: AsyncMutexGuard<'s, Workspaces<E>>,
+    modified: bool,
+}
+
+impl<E: Environment> std::ops::Deref for WorkspacesWriteGuard<'_, E> {
+    type Target = Workspaces<E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.workspaces
+    }
+}
+
+impl<E: Environment> std::ops::DerefMut for WorkspacesWriteGuard<'_, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
+        &mut self.workspaces
+    }
+}
+
+impl<E: Environment> Drop for WorkspacesWriteGuard<'_, E> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.shared
+                .snapshot
+                .store(Arc::new(self.workspaces.snapshot()));
+        }
+    }
+}
+
+/// Documents with changes that were received but are not applied yet.
+#[derive(Default)]
+pub(crate) struct PendingChanges(Mutex<HashMap<Url, usize>>);
+
+impl PendingChanges {
+    /// Marks the document as changed until the returned guard is dropped.
+    pub(crate) fn begin(&self, url: &Url) -> PendingChange<'_> {
+        *self.0.lock().unwrap().entry(url.clone()).or_default() += 1;
+
+        PendingChange {
+            changes: self,
+            url: url.clone(),
+        }
+    }
+
+    pub(crate) fn contains(&self, url: &Url) -> bool {
+        self.0.lock().unwrap().contains_key(url)
+    }
+}
+
+pub(crate) struct PendingChange<'p> {
+    changes: &'p PendingChanges,
+    url: Url,
+}
+
+impl Drop for PendingChange<'_> {
+    fn drop(&mut self) {
+        let mut changes = self.changes.0.lock().unwrap();
+
+        if let Some(count) = changes.get_mut(&self.url) {
+            *count -= 1;
+
+            if *count == 0 {
+                changes.remove(&self.url);
+            }
+        }
+    }
+}
+
+/// The workspaces by their roots.
 pub struct Workspaces<E: Environment>(IndexMap<Url, Workspace<E>>);
 
 impl<E: Environment> std::ops::Deref for Workspaces<E> {
@@ -57,32 +200,22 @@ impl<E: Environment> std::ops::Deref for Workspaces<E> {
     }
 }
 
-impl<E: Environment> std::ops::DerefMut for Workspaces<E> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl<E: Environment> Workspaces<E> {
+    /// Adds the workspace unless there is already one with the same root.
+    pub fn add(&mut self, ws: Workspace<E>) {
+        self.0.entry(ws.root.clone()).or_insert(ws);
     }
-}
 
-impl<E: Environment> Workspaces<E> {
-    #[must_use]
-    #[allow(clippy::missing_panics_doc)]
-    pub fn by_document(&self, url: &Url) -> &Workspace<E> {
-        self.0
-            .iter()
-            .filter(|(key, _)| {
-                let normalized_url = (*key).clone().normalize();
+    pub fn remove(&mut self, url: &Url) -> Option<Workspace<E>> {
+        self.0.shift_remove(url)
+    }
 
-                url.as_str().starts_with(key.as_str())
-                    || url.as_str().starts_with(normalized_url.as_str())
-            })
-            .max_by(|(a, _), (b, _)| a.as_str().len().cmp(&b.as_str().len()))
-            .map_or_else(
-                || {
-                    tracing::warn!(document_url = %url, "using detached workspace");
-                    self.0.get(&*DEFAULT_WORKSPACE_URL).unwrap()
-                },
-                |(_, ws)| ws,
-            )
+    pub fn get_mut(&mut self, url: &Url) -> Option<&mut Workspace<E>> {
+        self.0.get_mut(url).map(Workspace::modify)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Url, &mut Workspace<E>)> {
+        self.0.iter_mut().map(|(url, ws)| (url, ws.modify()))
     }
 
     #[allow(clippy::missing_panics_doc)]
@@ -102,27 +235,86 @@ impl<E: Environment> Workspaces<E> {
                     tracing::warn!(document_url = %url, "using detached workspace");
                 }
 
-                ws
+                ws.modify()
             })
             .unwrap()
     }
+
+    /// Snapshots of all workspaces, unmodified workspaces
+    /// share their snapshot with the previous ones.
+    fn snapshot(&mut self) -> WorkspacesSnapshot<E> {
+        WorkspacesSnapshot(
+            self.0
+                .iter_mut()
+                .map(|(url, ws)| (url.clone(), ws.snapshot()))
+                .collect(),
+        )
+    }
+}
+
+/// Immutable snapshots of the workspaces by their roots.
+pub struct WorkspacesSnapshot<E: Environment>(IndexMap<Url, Arc<WorkspaceSnapshot<E>>>);
+
+impl<E: Environment> std::ops::Deref for WorkspacesSnapshot<E> {
+    type Target = IndexMap<Url, Arc<WorkspaceSnapshot<E>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<E: Environment> WorkspacesSnapshot<E> {
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn by_document(&self, url: &Url) -> &WorkspaceSnapshot<E> {
+        self.0
+            .iter()
+            .filter(|(key, _)| {
+                let normalized_url = (*key).clone().normalize();
+
+                url.as_str().starts_with(key.as_str())
+                    || url.as_str().starts_with(normalized_url.as_str())
+            })
+            .max_by(|(a, _), (b, _)| a.as_str().len().cmp(&b.as_str().len()))
+            .map_or_else(
+                || {
+                    tracing::warn!(document_url = %url, "using detached workspace");
+                    self.0.get(&*DEFAULT_WORKSPACE_URL).unwrap()
+                },
+                |(_, ws)| ws,
+            )
+    }
 }
 
+/// A workspace that is modified by writers,
+/// requests are answered from its [`WorkspaceSnapshot`].
 #[allow(dead_code)]
 pub struct Workspace<E: Environment> {
     pub(crate) env: E,
     pub(crate) config: LspConfig,
-    pub(crate) rhai_config: Config,
+    pub(crate) rhai_config: Arc<Config>,
     pub(crate) root: Url,
-    pub(crate) documents: IndexMap<lsp_types::Url, Document>,
+    pub(crate) documents: IndexMap<lsp_types::Url, Arc<Document>>,
     pub(crate) hir: Hir,
+    /// The position encoding used for the documents.
+    pub(crate) position_encoding: PositionEncoding,
     /// A set of custom operators from definitions,
     /// along with their lhs and rhs types.
     pub(crate) custom_operators: HashSet<(String, Type, Type, (u8, u8))>,
+    /// How long the last parse pass took, see [`Workspace::parse_pass`].
+    pub(crate) last_parse: Option<Duration>,
+    /// Whether a parse pass is running.
+    in_parse_pass: bool,
+    /// How long the last resolve pass took.
+    pub(crate) last_resolve: Option<Duration>,
+    /// Incremented every time the HIR is resolved.
+    pub(crate) generation: u64,
+    /// The last snapshot, `None` if the workspace was modified since.
+    snapshot: Option<Arc<WorkspaceSnapshot<E>>>,
 }
 
 impl<E: Environment> Workspace<E> {
-    pub(crate) fn new(env: E, root: Url) -> Self {
+    pub(crate) fn new(env: E, root: Url, position_encoding: PositionEncoding) -> Self {
         tracing::info!(%root, "created workspace");
         Self {
             env,
@@ -131,26 +323,54 @@ impl<E: Environment> Workspace<E> {
             config: LspConfig::default(),
             documents: Default::default(),
             hir: Default::default(),
+            position_encoding,
             custom_operators: Default::default(),
+            last_parse: None,
+            in_parse_pass: false,
+            last_resolve: None,
+            generation: 0,
+            snapshot: None,
         }
     }
 }
 
 impl<E: Environment> Workspace<E> {
-    pub(crate) fn document(&self, url: &Url) -> Result<&Document, rpc::Error> {
-        self.documents
-            .get(url)
-            .or_else(|| self.documents.get(&url.clone().normalize()))
-            .ok_or_else(rpc::Error::invalid_params)
-    }
-
     pub(crate) fn is_detached(&self) -> bool {
         self.root == *DEFAULT_WORKSPACE_URL
     }
 
+    /// Marks the workspace as modified, so that a new snapshot is taken.
+    fn modify(&mut self) -> &mut Self {
+        self.snapshot = None;
+        self
+    }
+
+    /// The snapshot of the workspace, the HIR is only
+    /// copied if the workspace was modified since the last one.
+    fn snapshot(&mut self) -> Arc<WorkspaceSnapshot<E>> {
+        self.snapshot
+            .get_or_insert_with(|| {
+                Arc::new(WorkspaceSnapshot {
+                    env: self.env.clone(),
+                    config: self.config.clone(),
+                    rhai_config: self.rhai_config.clone(),
+                    root: self.root.clone(),
+                    documents: self.documents.clone(),
+                    hir: Arc::new(self.hir.clone()),
+                    custom_operators: self.custom_operators.clone(),
+                    last_parse: self.last_parse,
+                    last_resolve: self.last_resolve,
+                    generation: self.generation,
+                })
+            })
+            .clone()
+    }
+
     pub(crate) async fn load_rhai_config(&mut self) -> anyhow::Result<()> {
         self.rhai_config = Default::default();
 
+        let mut rhai_config = Config::default();
+
         let root_path = match self.env.url_to_file_path(&self.root) {
             Some(p) => p.normalize(),
             None => return Err(anyhow!("workspace root is not a valid file path")),
@@ -164,7 +384,7 @@ impl<E: Environment> Workspace<E> {
                 .await
                 .and_then(|v| toml::from_slice(&v).map_err(Into::into))
             {
-                Ok(c) => self.rhai_config = c,
+                Ok(c) => rhai_config = c,
                 Err(error) => {
                     tracing::error!(%error, "failed to read configuration");
                 }
@@ -173,79 +393,32 @@ impl<E: Environment> Workspace<E> {
             tracing::debug!("no config file found");
         }
 
-        self.rhai_config.prepare(&self.env, &root_path)
+        let prepared = rhai_config.prepare(&self.env, &root_path);
+        self.rhai_config = Arc::new(rhai_config);
+        prepared
     }
 
-    pub(crate) async fn load_all_files(&mut self) {
-        let includes = self.rhai_config.source.include.as_ref().unwrap();
-
-        let mut paths = Vec::new();
-
-        let workspace_root = match self.env.url_to_file_path(&self.root) {
-            Some(root) => root.normalize(),
-            None => {
-                tracing::debug!("workspace is not in a valid filesystem");
-                return;
-            }
+    /// Whether a change with the given version is older than the
+    /// document that is already loaded, documents opened by the client
+    /// are never replaced by their contents on disk.
+    pub(crate) fn is_outdated_change(&self, url: &Url, version: Option<i32>) -> bool {
+        let current = match self.documents.get(url) {
+            Some(doc) => doc.version,
+            None => return false,
         };
 
-        for include_pattern in includes {
-            let pattern_paths = match self
-                .env
-                .glob_files(&workspace_root.join(include_pattern).to_string_lossy())
-            {
-                Ok(paths) => paths.normalize(),
-                Err(error) => {
-                    tracing::error!(%error, "failed to load files");
-                    continue;
-                }
-            };
-
-            paths.extend(pattern_paths);
-        }
-
-        paths.dedup();
-
-        let all = paths.len();
-
-        if let Some(rule) = &self.rhai_config.source.file_rule {
-            paths.retain(|p| rule.is_match(p));
+        match (current, version) {
+            (Some(current), Some(version)) => version <= current,
+            (Some(_), None) => true,
+            (None, _) => false,
         }
+    }
 
-        let excluded = all - paths.len();
-
-        tracing::info!(count = all, excluded, "found files");
-
-        for path in paths {
-            if self.env.is_dir(&path) {
-                continue;
-            }
-            tracing::debug!(?path, "found file");
-
-            let document_url = Url::parse(&format!("file://{}", path.to_string_lossy())).unwrap();
-
-            let source = match self.env.read_file(&path).await {
-                Ok(src) => src,
-                Err(error) => {
-                    tracing::error!(%error, "failed to read file");
-                    continue;
-                }
-            };
-
-            let source_text = match String::from_utf8(source) {
-                Ok(s) => s,
-                Err(error) => {
-                    tracing::error!(%error, "given source is not valid UTF-8");
-                    continue;
-                }
-            };
-
-            self.add_document(document_url, &source_text);
-        }
-        self.hir.resolve_all();
+    pub fn add_document(&mut self, url: Url, text: &str, version: Option<i32>) {
+        self.parse_pass(|ws| ws.parse_document(url, text, version));
     }
 
-    pub fn add_document(&mut self, url: Url, text: &str) {
+    fn parse_document(&mut self, url: Url, text: &str, version: Option<i32>) {
         let is_def = is_rhai_def(text);
 
         let parse = if is_rhai_def(text) {
@@ -270,18 +443,26 @@ impl<E: Environment> Workspace<E> {
                 .parse_script()
         };
 
-        let mapper = Mapper::new_utf16(text, false);
+        let mapper = Mapper::new(text, self.position_encoding);
 
         let normalized_url = url.clone().normalize();
 
+        let semantic_tokens = self
+            .documents
+            .get(&url)
+            .map(|doc| doc.semantic_tokens.clone())
+            .unwrap_or_default();
+
         self.hir.add_source(&normalized_url, &parse.clone_syntax());
         self.documents.insert(
             url,
-            Document {
+            Arc::new(Document {
                 parse,
                 mapper,
                 is_def,
-            },
+                version,
+                semantic_tokens,
+            }),
         );
 
         if is_def {
@@ -301,6 +482,57 @@ impl<E: Environment> Workspace<E> {
         }
     }
 
+    /// Runs the given parsing as a single pass and records how long it took,
+    /// passes started inside it are part of it, e.g. scripts that are
+    /// parsed again after adding a definition with operators.
+    pub(crate) fn parse_pass<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        if self.in_parse_pass {
+            return f(self);
+        }
+
+        let start = self.env.now();
+        self.in_parse_pass = true;
+
+        let result = f(self);
+
+        self.in_parse_pass = false;
+        self.last_parse = Some(self.env.now().saturating_sub(start));
+
+        result
+    }
+
+    /// Resolves the HIR and records how long it took.
+    pub(crate) fn resolve_all(&mut self) {
+        let start = self.env.now();
+        self.hir.resolve_all();
+        self.last_resolve = Some(self.env.now().saturating_sub(start));
+        self.generation += 1;
+    }
+
+    /// Moves a document to a new URL without parsing it again.
+    pub(crate) fn rename_document(&mut self, old_url: &Url, new_url: Url) {
+        let doc = match self.documents.remove(old_url) {
+            Some(doc) => doc,
+            None => return,
+        };
+
+        let normalized_url = new_url.clone().normalize();
+
+        match self.hir.source_of(&old_url.clone().normalize()) {
+            Some(source) if self.hir.rename_source(source, &normalized_url) => {}
+            Some(source) => {
+                self.hir.remove_source(source);
+                self.hir
+                    .add_source(&normalized_url, &doc.parse.clone_syntax());
+            }
+            None => self
+                .hir
+                .add_source(&normalized_url, &doc.parse.clone_syntax()),
+        }
+
+        self.documents.insert(new_url, doc);
+    }
+
     /// Reparses scripts if the list of defined operators change.
     pub(crate) fn check_operators(&mut self) {
         let new_operators = self
@@ -326,15 +558,117 @@ impl<E: Environment> Workspace<E> {
                     self.hir.remove_source(src);
                 }
 
-                docs_to_reparse.push((uri.clone(), doc.parse.green.to_string()));
+                docs_to_reparse.push((uri.clone(), doc.parse.green.to_string(), doc.version));
             }
 
             doc.is_def
         });
 
-        for (uri, text) in docs_to_reparse {
-            self.add_document(uri, &text);
+        self.parse_pass(|ws| {
+            for (uri, text, version) in docs_to_reparse {
+                ws.add_document(uri, &text, version);
+            }
+        });
+    }
+}
+
+/// An immutable snapshot of a workspace that requests are answered from.
+///
+/// Documents and the HIR are shared with the workspace and other
+/// snapshots, they are only copied when the workspace is modified.
+pub struct WorkspaceSnapshot<E: Environment> {
+    pub(crate) env: E,
+    pub(crate) config: LspConfig,
+    pub(crate) rhai_config: Arc<Config>,
+    pub(crate) root: Url,
+    pub(crate) documents: IndexMap<lsp_types::Url, Arc<Document>>,
+    pub(crate) hir: Arc<Hir>,
+    /// See [`Workspace::custom_operators`].
+    pub(crate) custom_operators: HashSet<(String, Type, Type, (u8, u8))>,
+    pub(crate) last_parse: Option<Duration>,
+    pub(crate) last_resolve: Option<Duration>,
+    /// See [`Workspace::generation`].
+    pub(crate) generation: u64,
+}
+
+impl<E: Environment> WorkspaceSnapshot<E> {
+    pub(crate) fn document(&self, url: &Url) -> Result<&Document, rpc::Error> {
+        self.documents
+            .get(url)
+            .or_else(|| self.documents.get(&url.clone().normalize()))
+            .map(AsRef::as_ref)
+            .ok_or_else(rpc::Error::invalid_params)
+    }
+
+    pub(crate) fn is_detached(&self) -> bool {
+        self.root == *DEFAULT_WORKSPACE_URL
+    }
+
+    /// Whether the document is one of the files that
+    /// are loaded from the workspace.
+    pub(crate) fn is_source(&self, url: &Url) -> bool {
+        if self.is_detached() || self.rhai_config.source.include.is_none() {
+            return false;
+        }
+
+        let path = match self.env.url_to_file_path(url) {
+            Some(path) => path.normalize(),
+            None => return false,
+        };
+
+        self.rhai_config
+            .source
+            .file_rule
+            .as_ref()
+            .is_none_or(|rule| rule.is_match(&path))
+    }
+
+    /// Returns the paths of all files that are included in the workspace.
+    pub(crate) fn source_paths(&self) -> Vec<PathBuf> {
+        let includes = match self.rhai_config.source.include.as_ref() {
+            Some(includes) => includes,
+            None => return Vec::new(),
+        };
+
+        let mut paths = Vec::new();
+
+        let workspace_root = match self.env.url_to_file_path(&self.root) {
+            Some(root) => root.normalize(),
+            None => {
+                tracing::debug!("workspace is not in a valid filesystem");
+                return Vec::new();
+            }
+        };
+
+        for include_pattern in includes {
+            let pattern_paths = match self
+                .env
+                .glob_files(&workspace_root.join(include_pattern).to_string_lossy())
+            {
+                Ok(paths) => paths.normalize(),
+                Err(error) => {
+                    tracing::error!(%error, "failed to load files");
+                    continue;
+                }
+            };
+
+            paths.extend(pattern_paths);
         }
+
+        paths.dedup();
+
+        let all = paths.len();
+
+        if let Some(rule) = &self.rhai_config.source.file_rule {
+            paths.retain(|p| rule.is_match(p));
+        }
+
+        let excluded = all - paths.len();
+
+        tracing::info!(count = all, excluded, "found files");
+
+        paths.retain(|path| !self.env.is_dir(path));
+        paths
     }
 }
 
@@ -343,4 +677,16 @@ pub struct Document {
     pub(crate) parse: Parse,
     pub(crate) mapper: Mapper,
     pub(crate) is_def: bool,
+    /// The version of the document from the client,
+    /// or `None` if it was loaded from disk.
+    pub(crate) version: Option<i32>,
+    /// The last semantic tokens sent to the client,
+    /// kept across changes for delta requests.
+    pub(crate) semantic_tokens: Arc<Mutex<SemanticTokensCache>>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SemanticTokensCache {
+    pub(crate) result_id: u64,
+    pub(crate) tokens: Vec<SemanticToken>,
 }
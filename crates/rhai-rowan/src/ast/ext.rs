@@ -7,7 +7,8 @@ use rowan::NodeOrToken;
 
 use super::{
     AstNode, DefOpPrecedence, Expr, Lit, LitStrTemplate, LitStrTemplateInterpolation, ObjectField,
-    Param, ParamList, Stmt, SwitchArm, SwitchArmCondition, Type, TypeObjectField, TypedParam,
+    Param, ParamList, Stmt, SwitchArm, SwitchArmCondition, Type, TypeGenerics, TypeObjectField,
+    TypedParam,
 };
 use super::{ExprBlock, ExprIf, T};
 use crate::syntax::{SyntaxElement, SyntaxKind, SyntaxToken};
@@ -449,6 +450,26 @@ impl super::DefConst {
     }
 }
 
+impl super::TypeIdent {
+    #[must_use]
+    pub fn generics(&self) -> Option<TypeGenerics> {
+        self.syntax().children().find_map(TypeGenerics::cast)
+    }
+}
+
+impl super::TypeFn {
+    #[must_use]
+    pub fn ret_ty(&self) -> Option<Type> {
+        self.syntax().children().find_map(Type::cast)
+    }
+}
+
+impl super::TypeGenerics {
+    pub fn types(&self) -> impl Iterator<Item = Type> {
+        self.syntax().children().filter_map(Type::cast)
+    }
+}
+
 impl super::TypeList {
     pub fn types(&self) -> impl Iterator<Item = Type> {
         self.syntax().children().filter_map(Type::cast)
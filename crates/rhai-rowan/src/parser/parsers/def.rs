@@ -449,7 +449,7 @@ fn parse_typed_param(ctx: &mut Context) {
 }
 
 #[tracing::instrument(level = tracing::Level::TRACE, skip(ctx))]
-fn parse_type_list(ctx: &mut Context) {
+pub(super) fn parse_type_list(ctx: &mut Context) {
     ctx.start_node(TYPE_LIST);
 
     let start_token = require_token!(ctx in node);
@@ -154,8 +154,27 @@ fn parse_type_tuple(ctx: &mut Context) {
 
 #[tracing::instrument(level = tracing::Level::TRACE, skip(ctx))]
 fn parse_type_ident(ctx: &mut Context) {
-    ctx.start_node(TYPE_IDENT);
-    expect_token!(ctx in node, T!["ident"]);
+    let start = ctx.checkpoint();
+
+    // The caller has already checked that this is an identifier.
+    ctx.eat();
+
+    // Function types, e.g. `Fn(int, String) -> bool`.
+    if let Some(T!["("]) = ctx.token() {
+        ctx.start_node_at(start, TYPE_FN);
+
+        super::def::parse_type_list(ctx);
+
+        if let Some(T!["->"]) = ctx.token() {
+            ctx.eat();
+            parse_type(ctx);
+        }
+
+        ctx.finish_node();
+        return;
+    }
+
+    ctx.start_node_at(start, TYPE_IDENT);
 
     if let Some(T!["<"]) = ctx.token() {
         parse_type_generics(ctx);
@@ -1,6 +1,6 @@
 //! Cursor queries of a document purely based on syntax.
 
-use rowan::{NodeOrToken, TextSize};
+use rowan::{NodeOrToken, TextRange, TextSize};
 
 use crate::{
     ast::{AstNode, Path},
@@ -12,6 +12,9 @@ use self::util::SyntaxExt;
 
 mod util;
 
+pub mod selection;
+pub use selection::selection_ranges;
+
 #[derive(Debug, Default)]
 pub struct Query {
     /// The offset the query was made for.
@@ -136,6 +139,30 @@ impl Query {
         Some(binary_expr)
     }
 
+    /// The range of the expression on the left side of
+    /// a field access without surrounding whitespace and comments.
+    #[must_use]
+    pub fn field_access_receiver_range(&self) -> Option<TextRange> {
+        if !self.is_field_access() {
+            return None;
+        }
+
+        let lhs = self.binary_expr()?.first_child()?;
+
+        let mut tokens = lhs
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .filter(|t| !matches!(t.kind(), WHITESPACE | COMMENT_LINE | COMMENT_BLOCK));
+
+        let first = tokens.next()?;
+        let last = tokens.last().unwrap_or_else(|| first.clone());
+
+        Some(TextRange::new(
+            first.text_range().start(),
+            last.text_range().end(),
+        ))
+    }
+
     #[must_use]
     pub fn is_path(&self) -> bool {
         self.path_node().is_some()
@@ -182,6 +209,200 @@ impl Query {
         }
     }
 
+    /// The range of the import path from the opening quote
+    /// up to the cursor if the cursor is inside the path string
+    /// of an import, e.g. `import "./fo$$"`.
+    ///
+    /// Unterminated strings are also recognized
+    /// until the end of the line.
+    #[must_use]
+    pub fn import_path_range(&self) -> Option<TextRange> {
+        let before = self.before.as_ref()?;
+
+        let path_expr = before
+            .syntax
+            .parent_ancestors()
+            .find(|n| n.kind() == EXPR_IMPORT)?
+            .children()
+            .find(|n| n.kind() == EXPR)?;
+
+        let quote = path_expr
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .find(|t| !matches!(t.kind(), WHITESPACE | COMMENT_LINE | COMMENT_BLOCK))?;
+
+        let quote_range = quote.text_range();
+
+        if self.offset <= quote_range.start() {
+            return None;
+        }
+
+        match quote.kind() {
+            LIT_STR if quote.text().starts_with('"') => {
+                let terminated = quote.text().len() > 1 && quote.text().ends_with('"');
+
+                let in_string = if terminated {
+                    self.offset < quote_range.end()
+                } else {
+                    self.offset <= quote_range.end()
+                };
+
+                if !in_string {
+                    return None;
+                }
+            }
+            ERROR if quote.text() == "\"" => {
+                let path = path_expr.text().to_string();
+                let path_start = path_expr.text_range().start();
+                let typed_end = usize::from(self.offset - path_start).min(path.len());
+
+                if self.offset > path_expr.text_range().end()
+                    || path[usize::from(quote_range.end() - path_start)..typed_end].contains('\n')
+                {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+
+        Some(TextRange::new(
+            quote_range.start() + TextSize::from(1),
+            self.offset,
+        ))
+    }
+
+    /// Whether the cursor is at the start of a statement,
+    /// either in an empty position or in the identifier
+    /// that begins the statement.
+    #[must_use]
+    pub fn is_stmt_start(&self) -> bool {
+        let before = match &self.before {
+            Some(before) => before,
+            None => return true,
+        };
+
+        match before.syntax.kind() {
+            IDENT => {
+                before.syntax.parent().map(|p| p.kind()) == Some(RHAI_DEF)
+                    || Self::stmt_of_ident(&before.syntax).is_some()
+            }
+            WHITESPACE | COMMENT_LINE | COMMENT_BLOCK | T!["{"] | T![";"] => {
+                match before.syntax.parent().map(|p| p.kind()) {
+                    Some(RHAI | RHAI_DEF | EXPR_BLOCK) => true,
+                    Some(STMT) => before.syntax.kind() == T![";"],
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the cursor is in the body of a loop
+    /// of the current function or closure.
+    #[must_use]
+    pub fn is_in_loop(&self) -> bool {
+        let before = match &self.before {
+            Some(before) => before,
+            None => return false,
+        };
+
+        for node in before.syntax.parent_ancestors() {
+            match node.kind() {
+                EXPR_FN | EXPR_CLOSURE => return false,
+                EXPR_BLOCK => {
+                    if let Some(EXPR_LOOP | EXPR_FOR | EXPR_WHILE) = node.parent().map(|p| p.kind())
+                    {
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    /// Whether the statement at the cursor directly follows
+    /// an `if` expression that has no `else` branch yet.
+    #[must_use]
+    pub fn is_after_if(&self) -> bool {
+        if !self.is_stmt_start() {
+            return false;
+        }
+
+        let before = match &self.before {
+            Some(before) => before,
+            None => return false,
+        };
+
+        let prev_stmt = if before.syntax.kind() == IDENT {
+            Self::stmt_of_ident(&before.syntax).and_then(|stmt| stmt.prev_sibling())
+        } else {
+            before
+                .syntax
+                .prev_sibling_or_token()
+                .and_then(|prev| match prev {
+                    NodeOrToken::Node(n) => Some(n),
+                    NodeOrToken::Token(t) => t.prev_sibling_or_token()?.into_node(),
+                })
+        };
+
+        let prev_stmt = match prev_stmt {
+            Some(stmt) if stmt.kind() == STMT => stmt,
+            _ => return false,
+        };
+
+        if prev_stmt
+            .children_with_tokens()
+            .any(|t| t.kind() == T![";"])
+        {
+            return false;
+        }
+
+        let mut expr_if = prev_stmt
+            .descendants()
+            .find(|n| !matches!(n.kind(), STMT | ITEM | DOC | EXPR))
+            .filter(|n| n.kind() == EXPR_IF);
+
+        while let Some(if_node) = expr_if {
+            if !if_node
+                .children_with_tokens()
+                .any(|t| t.kind() == T!["else"])
+            {
+                return true;
+            }
+
+            expr_if = if_node.children().find(|n| n.kind() == EXPR_IF);
+        }
+
+        false
+    }
+
+    /// The statement that begins with the given identifier.
+    fn stmt_of_ident(ident: &SyntaxToken) -> Option<SyntaxNode> {
+        let stmt = ident
+            .parent_ancestors()
+            .take_while(|n| matches!(n.kind(), EXPR_IDENT | EXPR | ITEM | STMT))
+            .last()
+            .filter(|n| n.kind() == STMT)?;
+
+        let first_token = stmt
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .find(|t| {
+                !matches!(
+                    t.kind(),
+                    WHITESPACE
+                        | COMMENT_LINE
+                        | COMMENT_BLOCK
+                        | COMMENT_LINE_DOC
+                        | COMMENT_BLOCK_DOC
+                )
+            })?;
+
+        (first_token == *ident).then_some(stmt)
+    }
+
     #[must_use]
     pub fn can_complete_ref(&self) -> bool {
         if self.can_complete_op() {
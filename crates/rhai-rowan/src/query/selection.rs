@@ -0,0 +1,164 @@
+use rowan::{NodeOrToken, TextRange, TextSize, TokenAtOffset};
+
+use crate::syntax::{SyntaxElement, SyntaxKind::*, SyntaxNode, SyntaxToken};
+
+/// Returns the ranges for expanding the selection at the given offset,
+/// the innermost range first.
+///
+/// Each range strictly contains the previous one.
+#[must_use]
+pub fn selection_ranges(root: &SyntaxNode, offset: TextSize) -> Vec<TextRange> {
+    let token = match root.token_at_offset(offset) {
+        TokenAtOffset::None => return Vec::new(),
+        TokenAtOffset::Single(t) => t,
+        TokenAtOffset::Between(left, right) => {
+            if is_trivia(&right) && !is_trivia(&left) {
+                left
+            } else {
+                right
+            }
+        }
+    };
+
+    let mut ranges: Vec<TextRange> = Vec::new();
+
+    let mut push_range = |range: TextRange| match ranges.last() {
+        Some(last) if !range.contains_range(*last) || range == *last => {}
+        _ => ranges.push(range),
+    };
+
+    if !is_trivia(&token) {
+        if let Some(inner) = string_contents(&token) {
+            push_range(inner);
+        }
+
+        push_range(token.text_range());
+    }
+
+    for node in token.parent_ancestors() {
+        if let Some(inner) = delimited_contents(&node) {
+            push_range(inner);
+        }
+
+        if let Some(range) = trimmed_range(&SyntaxElement::Node(node)) {
+            push_range(range);
+        }
+    }
+
+    ranges
+}
+
+fn is_trivia(token: &SyntaxToken) -> bool {
+    matches!(
+        token.kind(),
+        WHITESPACE | COMMENT_LINE | COMMENT_BLOCK | COMMENT_LINE_DOC | COMMENT_BLOCK_DOC
+    )
+}
+
+/// The contents of a string literal without the quotes.
+fn string_contents(token: &SyntaxToken) -> Option<TextRange> {
+    if token.kind() != LIT_STR {
+        return None;
+    }
+
+    let text = token.text();
+
+    // Parts of string templates are handled by the template node.
+    if !text.starts_with('"') || !text.ends_with('"') || text.len() < 2 {
+        return None;
+    }
+
+    let range = token.text_range();
+
+    Some(TextRange::new(
+        range.start() + TextSize::from(1),
+        range.end() - TextSize::from(1),
+    ))
+}
+
+/// The contents of a node between its delimiters,
+/// e.g. the arguments of an argument list without the parentheses.
+fn delimited_contents(node: &SyntaxNode) -> Option<TextRange> {
+    if node.kind() == LIT_STR_TEMPLATE {
+        let range = node.text_range();
+
+        if range.len() < TextSize::from(2) {
+            return None;
+        }
+
+        return Some(TextRange::new(
+            range.start() + TextSize::from(1),
+            range.end() - TextSize::from(1),
+        ));
+    }
+
+    if !matches!(
+        node.kind(),
+        ARG_LIST
+            | PARAM_LIST
+            | TYPED_PARAM_LIST
+            | EXPR_ARRAY
+            | EXPR_OBJECT
+            | EXPR_BLOCK
+            | EXPR_PAREN
+            | LIT_STR_TEMPLATE_INTERPOLATION
+    ) {
+        return None;
+    }
+
+    let children = node
+        .children_with_tokens()
+        .filter(|child| match child {
+            NodeOrToken::Node(_) => true,
+            NodeOrToken::Token(t) => !is_trivia(t),
+        })
+        .collect::<Vec<_>>();
+
+    let contents = match children.as_slice() {
+        [first, contents @ .., last] if is_delimiter(first) && is_delimiter(last) => contents,
+        // The interpolation node does not include the delimiters.
+        contents if node.kind() == LIT_STR_TEMPLATE_INTERPOLATION => contents,
+        _ => return None,
+    };
+
+    Some(TextRange::new(
+        trimmed_range(contents.first()?)?.start(),
+        trimmed_range(contents.last()?)?.end(),
+    ))
+}
+
+/// The range of the element without leading and trailing
+/// whitespace and comments.
+fn trimmed_range(element: &SyntaxElement) -> Option<TextRange> {
+    match element {
+        NodeOrToken::Node(node) => {
+            let mut tokens = node
+                .descendants_with_tokens()
+                .filter_map(SyntaxElement::into_token)
+                .filter(|t| !is_trivia(t));
+
+            let first = tokens.next()?;
+            let last = tokens.last().unwrap_or_else(|| first.clone());
+
+            Some(TextRange::new(
+                first.text_range().start(),
+                last.text_range().end(),
+            ))
+        }
+        NodeOrToken::Token(token) => Some(token.text_range()),
+    }
+}
+
+fn is_delimiter(element: &SyntaxElement) -> bool {
+    matches!(
+        element.kind(),
+        PUNCT_PAREN_START
+            | PUNCT_PAREN_END
+            | PUNCT_BRACKET_START
+            | PUNCT_BRACKET_END
+            | PUNCT_BRACE_START
+            | PUNCT_BRACE_END
+            | PUNCT_MAP_START
+            | OP_BIT_OR
+    )
+}
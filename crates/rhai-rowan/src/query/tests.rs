@@ -331,3 +331,109 @@ fn test_complete_ref_fails_in_decl() {
         assert!(!q.can_complete_ref(), "test failed for index {idx}",);
     }
 }
+
+#[test]
+fn test_selection_ranges_arg_list() {
+    let (offset, src) = src_cursor_offset(r#"foo(a, #{ b: "c$$" })"#);
+
+    let syntax = Parser::new(&src).parse_script().into_syntax();
+
+    let ranges = selection_ranges(&syntax, offset)
+        .into_iter()
+        .map(|range| &src[range])
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        ranges,
+        [
+            "c",
+            r#""c""#,
+            r#"b: "c""#,
+            r#"#{ b: "c" }"#,
+            r#"a, #{ b: "c" }"#,
+            r#"(a, #{ b: "c" })"#,
+            r#"foo(a, #{ b: "c" })"#,
+        ]
+    );
+}
+
+#[test]
+fn test_query_import_path() {
+    let sources = [
+        (r#"import "./fo$$" as foo;"#, Some("./fo")),
+        (r#"import "$$";"#, Some("")),
+        ("import \"../$$", Some("../")),
+        ("import \"./a/$$\nlet a = 2;", Some("./a/")),
+        (r#"import "./foo"$$ as foo;"#, None),
+        (r#"import $$"./foo";"#, None),
+        (r#"let a = "./fo$$";"#, None),
+    ];
+
+    for (src, expected) in sources {
+        let (offset, src) = src_cursor_offset(src);
+
+        let syntax = Parser::new(&src).parse_script().into_syntax();
+
+        let q = Query::at(&syntax, offset);
+
+        assert_eq!(
+            q.import_path_range().map(|range| &src[range]),
+            expected,
+            "test failed for {src:?}"
+        );
+    }
+}
+
+#[test]
+fn test_query_keyword_positions() {
+    let sources = [
+        // (source, statement start, in loop, after if)
+        ("fo$$", true, false, false),
+        ("let a = fo$$", false, false, false),
+        ("while x { br$$ }", true, true, false),
+        ("if x {} el$$", true, false, true),
+        ("if a {} else if b {} el$$", true, false, true),
+        ("if a {} else {} el$$", true, false, false),
+        ("loop { let f = || { br$$ }; }", true, false, false),
+        ("for x in y { if z { co$$ } }", true, true, false),
+        ("foo(ba$$)", false, false, false),
+    ];
+
+    for (src, stmt_start, in_loop, after_if) in sources {
+        let (offset, src) = src_cursor_offset(src);
+
+        let syntax = Parser::new(&src).parse_script().into_syntax();
+
+        let q = Query::at(&syntax, offset);
+
+        assert_eq!(
+            (q.is_stmt_start(), q.is_in_loop(), q.is_after_if()),
+            (stmt_start, in_loop, after_if),
+            "test failed for {src:?}"
+        );
+    }
+}
+
+#[test]
+fn test_query_field_access_receiver() {
+    let sources = [
+        ("let x = (1..10).fo$$", Some("(1..10)")),
+        ("let x =   y.$$", Some("y")),
+        ("foo.bar.ba$$z", Some("foo.bar")),
+        ("let x = fo$$", None),
+    ];
+
+    for (src, expected) in sources {
+        let (offset, src) = src_cursor_offset(src);
+
+        let syntax = Parser::new(&src).parse_script().into_syntax();
+
+        let q = Query::at(&syntax, offset);
+
+        assert_eq!(
+            q.field_access_receiver_range().map(|range| &src[range]),
+            expected,
+            "test failed for {src:?}"
+        );
+    }
+}
@@ -412,6 +412,7 @@ pub enum SyntaxKind {
     TYPE_ARRAY,
     TYPE_TUPLE,
     TYPE_UNKNOWN,
+    TYPE_FN,
     TYPE_GENERICS,
     TYPE_OBJECT_FIELD,
     TYPED_PARAM,
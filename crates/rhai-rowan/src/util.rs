@@ -1,6 +1,9 @@
-use crate::parser::{
-    parsers::{self, def::parse_def_header, parse_expr},
-    Parse, Parser,
+use crate::{
+    parser::{
+        parsers::{self, def::parse_def_header, parse_expr},
+        Parse, Parser,
+    },
+    syntax::{Lexer, SyntaxKind},
 };
 use rowan::{TextRange, TextSize};
 use thiserror::Error;
@@ -96,7 +99,27 @@ pub fn is_rhai_def(source: &str) -> bool {
 pub fn is_valid_ident(text: &str) -> bool {
     let mut ident_parser = Parser::new(text);
     ident_parser.execute(parsers::parse_expr_ident);
-    ident_parser.finish().errors.is_empty()
+
+    // The parser stops after the identifier,
+    // anything after it is not part of it.
+    ident_parser.finish().errors.is_empty() && Lexer::new(text).nth(1).is_none()
+}
+
+/// Returns the kind of the keyword if the given text
+/// is a single keyword, reserved keywords included.
+#[must_use]
+pub fn keyword_kind(text: &str) -> Option<SyntaxKind> {
+    let mut lexer = Lexer::new(text);
+    let kind = lexer.next()?;
+
+    if kind == SyntaxKind::IDENT
+        || lexer.span() != (0..text.len())
+        || !text.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    Some(kind)
 }
 
 #[must_use]
@@ -302,3 +325,24 @@ fn test_cursor_offsets() {
             .collect::<Vec<_>>()
     );
 }
+
+#[cfg(test)]
+#[test]
+fn test_is_valid_ident() {
+    assert!(is_valid_ident("value"));
+    assert!(is_valid_ident("_value2"));
+    assert!(!is_valid_ident("let"));
+    assert!(!is_valid_ident("a b"));
+    assert!(!is_valid_ident("a.b"));
+    assert!(!is_valid_ident("1a"));
+}
+
+#[cfg(test)]
+#[test]
+fn test_keyword_kind() {
+    assert_eq!(keyword_kind("let"), Some(SyntaxKind::KW_LET));
+    assert!(keyword_kind("async").map_or(false, |kind| kind.is_reserved_keyword()));
+    assert_eq!(keyword_kind("value"), None);
+    assert_eq!(keyword_kind("let x"), None);
+    assert_eq!(keyword_kind("+"), None);
+}
@@ -10,9 +10,16 @@ use std::{
 };
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use url::Url;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 
+#[wasm_bindgen]
+extern "C" {
+    /// A monotonic clock in milliseconds.
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
 pub(crate) struct JsAsyncRead {
     fut: Option<JsFuture>,
     f: Function,
@@ -368,4 +375,8 @@ impl Environment for WasmEnvironment {
             .await
             .map_err(|err| anyhow!("{:?}", err));
     }
+
+    fn now(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(performance_now() / 1000.0)
+    }
 }
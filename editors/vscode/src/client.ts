@@ -1,6 +1,10 @@
 import * as vscode from "vscode";
 import * as node from "vscode-languageclient/node";
-import { Middleware, ProvideCodeLensesSignature } from "vscode-languageclient/node";
+import {
+  Middleware,
+  ProvideCodeLensesSignature,
+  ResolveCodeLensSignature,
+} from "vscode-languageclient/node";
 import which from "which";
 import { getOutput } from "./util";
 
@@ -104,36 +108,6 @@ class ClientMiddleware implements Middleware {
     next: ProvideCodeLensesSignature
   ): vscode.ProviderResult<Array<vscode.CodeLens>> {
     const resolvedCodeLenses = next(doc, token);
-    const fixCodeLens = (codeLensToFix: vscode.CodeLens): vscode.CodeLens => {
-      if (codeLensToFix.command?.command === "editor.action.showReferences") {
-        const oldArgs = codeLensToFix.command.arguments;
-
-        // Our JSON objects don't get handled correctly by
-        // VS Code's built in editor.action.showReferences
-        // command so we need to convert them into the
-        // appropriate types to send them as command
-        // arguments.
-
-        codeLensToFix.command.arguments = [
-          vscode.Uri.parse(oldArgs[0]),
-          new vscode.Position(oldArgs[1].line, oldArgs[1].character),
-          oldArgs[2].map(position => {
-            return new vscode.Location(
-              vscode.Uri.parse(position.uri),
-              new vscode.Range(
-                position.range.start.line,
-                position.range.start.character,
-                position.range.end.line,
-                position.range.end.character
-              )
-            );
-          }),
-        ];
-      }
-
-      return codeLensToFix;
-    };
-
     if (isThenable<Array<vscode.CodeLens>>(resolvedCodeLenses)) {
       return resolvedCodeLenses.then(r => r.map(fixCodeLens));
     } else if (is<Array<vscode.CodeLens>>(resolvedCodeLenses)) {
@@ -142,6 +116,54 @@ class ClientMiddleware implements Middleware {
 
     return resolvedCodeLenses;
   }
+
+  // The reference count lenses are resolved lazily,
+  // so their commands also have to be fixed here.
+  public resolveCodeLens(
+    codeLens: vscode.CodeLens,
+    token: vscode.CancellationToken,
+    next: ResolveCodeLensSignature
+  ): vscode.ProviderResult<vscode.CodeLens> {
+    const resolvedCodeLens = next(codeLens, token);
+
+    if (isThenable<vscode.CodeLens>(resolvedCodeLens)) {
+      return resolvedCodeLens.then(r => r && fixCodeLens(r));
+    } else if (is<vscode.CodeLens>(resolvedCodeLens)) {
+      return fixCodeLens(resolvedCodeLens);
+    }
+
+    return resolvedCodeLens;
+  }
+}
+
+function fixCodeLens(codeLensToFix: vscode.CodeLens): vscode.CodeLens {
+  if (codeLensToFix.command?.command === "editor.action.showReferences") {
+    const oldArgs = codeLensToFix.command.arguments;
+
+    // Our JSON objects don't get handled correctly by
+    // VS Code's built in editor.action.showReferences
+    // command so we need to convert them into the
+    // appropriate types to send them as command
+    // arguments.
+
+    codeLensToFix.command.arguments = [
+      vscode.Uri.parse(oldArgs[0]),
+      new vscode.Position(oldArgs[1].line, oldArgs[1].character),
+      oldArgs[2].map(position => {
+        return new vscode.Location(
+          vscode.Uri.parse(position.uri),
+          new vscode.Range(
+            position.range.start.line,
+            position.range.start.character,
+            position.range.end.line,
+            position.range.end.character
+          )
+        );
+      }),
+    ];
+  }
+
+  return codeLensToFix;
 }
 
 function isThenable<T>(obj: any): obj is Thenable<T> {